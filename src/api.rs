@@ -0,0 +1,231 @@
+use rocket::State;
+use rocket::request::LenientForm;
+use rocket_contrib::json::Json;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+
+use event_manager::EventManager;
+use events::{Event, EventType, Payload, Source, SourceStatus};
+use query::EventQuery;
+use stats;
+
+// The types in this module are the stable JSON representation of the
+// API. They are deliberately decoupled from GitHub's schema and from the
+// format of the event history.
+
+#[derive(Serialize)]
+pub struct ApiSource {
+    kind: &'static str,
+    name: String,
+}
+
+impl<'a> From<&'a Source> for ApiSource {
+    fn from(source: &'a Source) -> Self {
+        match *source {
+            Source::User(ref login) => ApiSource { kind: "user", name: login.clone() },
+            Source::Org(ref org) => ApiSource { kind: "org", name: org.clone() },
+            Source::Repo(ref repo) => ApiSource { kind: "repo", name: repo.clone() },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApiUser {
+    login: String,
+    avatar_url: String,
+    html_url: String,
+}
+
+#[derive(Serialize)]
+pub struct ApiRepository {
+    full_name: String,
+    html_url: String,
+}
+
+impl ApiRepository {
+    fn new(full_name: &str) -> Self {
+        ApiRepository {
+            full_name: full_name.to_string(),
+            html_url: format!("https://github.com/{}", full_name),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApiRelease {
+    tag_name: String,
+    name: Option<String>,
+    html_url: String,
+}
+
+#[derive(Serialize)]
+pub struct ApiEvent {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: &'static str,
+    created_at: DateTime<Utc>,
+    summary: String,
+    html_url: String,
+    actor: ApiUser,
+    repository: ApiRepository,
+    /// The created fork of fork events
+    fork: Option<ApiRepository>,
+    /// The published release of release events
+    release: Option<ApiRelease>,
+    /// The repository description of create events
+    description: Option<String>,
+    source: ApiSource,
+}
+
+fn event_type_name(event_type: EventType) -> &'static str {
+    match event_type {
+        EventType::Fork => "fork",
+        EventType::Watch => "star",
+        EventType::Create => "create",
+        EventType::Release => "release",
+        EventType::Public => "public",
+    }
+}
+
+impl<'a> From<&'a Event> for ApiEvent {
+    fn from(event: &'a Event) -> Self {
+        let (fork, release, description) = match event.payload {
+            Payload::Fork { ref forkee } => (
+                Some(ApiRepository {
+                    full_name: forkee.full_name.clone(),
+                    html_url: forkee.html_url.clone(),
+                }),
+                None,
+                None,
+            ),
+            Payload::Release { ref release } => (
+                None,
+                Some(ApiRelease {
+                    tag_name: release.tag_name.clone(),
+                    name: release.name.clone(),
+                    html_url: release.html_url.clone(),
+                }),
+                None,
+            ),
+            Payload::Create { ref description } => (None, None, description.clone()),
+            Payload::Watch | Payload::Public => (None, None, None),
+        };
+
+        ApiEvent {
+            id: event.id.clone(),
+            event_type: event_type_name(event.payload.event_type()),
+            created_at: event.created_at,
+            summary: event.summary(),
+            html_url: event.html_url(),
+            actor: ApiUser {
+                login: event.actor.display_login.clone(),
+                avatar_url: event.actor.avatar_url.clone(),
+                html_url: format!("https://github.com/{}", event.actor.display_login),
+            },
+            repository: ApiRepository::new(&event.repo.name),
+            fork: fork,
+            release: release,
+            description: description,
+            source: ApiSource::from(&event.source),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApiEventPage {
+    page: usize,
+    per_page: usize,
+    /// Number of events matching the filters
+    total: usize,
+    events: Vec<ApiEvent>,
+    /// Link to the next (older) page, stable while new events arrive
+    next: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ApiSourceStatus {
+    source: ApiSource,
+    last_success: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    stale: bool,
+    events: usize,
+}
+
+#[derive(Serialize)]
+pub struct ApiCount {
+    name: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+pub struct ApiStats {
+    events: usize,
+    forks: usize,
+    first_event: Option<DateTime<Utc>>,
+    last_event: Option<DateTime<Utc>>,
+    forks_per_user: Vec<ApiCount>,
+    most_forked_repositories: Vec<ApiCount>,
+}
+
+fn counts(counts: Vec<(String, usize)>) -> Vec<ApiCount> {
+    counts
+        .into_iter()
+        .map(|(name, count)| ApiCount {
+            name: name,
+            count: count,
+        })
+        .collect()
+}
+
+#[get("/api/v1/events?<query..>")]
+pub fn events(query: LenientForm<EventQuery>, event_manager: State<Arc<Mutex<EventManager>>>) -> Json<ApiEventPage> {
+    debug!("Handling /api/v1/events request");
+    let event_manager = event_manager.inner().lock().unwrap();
+    let page = query.paginate("/api/v1/events", &event_manager.events);
+
+    Json(ApiEventPage {
+        page: query.page(),
+        per_page: query.per_page(),
+        total: page.total,
+        events: page.events.into_iter().map(ApiEvent::from).collect(),
+        next: page.older,
+    })
+}
+
+#[get("/api/v1/users")]
+pub fn users(event_manager: State<Arc<Mutex<EventManager>>>) -> Json<Vec<ApiSourceStatus>> {
+    debug!("Handling /api/v1/users request");
+    let event_manager = event_manager.inner().lock().unwrap();
+
+    Json(
+        event_manager.status
+            .iter()
+            .map(|status: &SourceStatus| ApiSourceStatus {
+                source: ApiSource::from(&status.source),
+                last_success: status.last_success,
+                last_error: status.last_error.clone(),
+                stale: status.stale,
+                events: event_manager.events
+                    .iter()
+                    .filter(|event| event.source == status.source)
+                    .count(),
+            })
+            .collect()
+    )
+}
+
+#[get("/api/v1/stats")]
+pub fn stats(event_manager: State<Arc<Mutex<EventManager>>>) -> Json<ApiStats> {
+    debug!("Handling /api/v1/stats request");
+    let event_manager = event_manager.inner().lock().unwrap();
+    let events = &event_manager.events;
+
+    Json(ApiStats {
+        events: events.len(),
+        forks: stats::forks(events).count(),
+        first_event: events.first().map(|event| event.created_at),
+        last_event: events.last().map(|event| event.created_at),
+        forks_per_user: counts(stats::forks_per_user(events)),
+        most_forked_repositories: counts(stats::forks_per_repository(events)),
+    })
+}
@@ -0,0 +1,255 @@
+use rocket::Request;
+use rocket::http::{ContentType, Status};
+use rocket::response::{self, Responder, Response};
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Write};
+
+use events::Event;
+use feed::FEED_ENTRIES;
+
+/// Format of the `Last-Modified` and `If-Modified-Since` headers.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// A feed document that supports conditional requests and gzip compression.
+///
+/// The validators are derived from the events included in the feed, so the
+/// feed only changes when one of its entries does.
+pub struct CachedFeed {
+    content_type: ContentType,
+    body: String,
+    etag: String,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+impl CachedFeed {
+    /// `events` are all events the feed was built from, oldest first.
+    pub fn new(content_type: ContentType, body: String, events: &[Event]) -> Self {
+        let entries = &events[events.len().saturating_sub(FEED_ENTRIES)..];
+        let mut hasher = DefaultHasher::new();
+
+        content_type.to_string().hash(&mut hasher);
+        for event in entries {
+            event.id.hash(&mut hasher);
+        }
+
+        CachedFeed {
+            content_type: content_type,
+            body: body,
+            etag: format!("{:016x}", hasher.finish()),
+            last_modified: entries.iter().map(|event| event.created_at).max(),
+        }
+    }
+
+    fn is_not_modified(&self, request: &Request, etag: &str) -> bool {
+        if let Some(if_none_match) = request.headers().get_one("If-None-Match") {
+            return if_none_match
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag);
+        }
+
+        match (request.headers().get_one("If-Modified-Since"), self.last_modified) {
+            (Some(since), Some(last_modified)) => DateTime::parse_from_rfc2822(since)
+                .map(|since| last_modified.timestamp() <= since.timestamp())
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+fn accepts_gzip(request: &Request) -> bool {
+    request.headers()
+        .get("Accept-Encoding")
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';').map(|part| part.trim());
+            let name = parts.next().unwrap_or("");
+            // `gzip;q=0` explicitly refuses gzip
+            let refused = parts
+                .filter(|param| param.starts_with("q="))
+                .any(|param| param[2..].parse::<f32>().map_or(false, |q| q == 0.0));
+
+            name.eq_ignore_ascii_case("gzip") && !refused
+        })
+}
+
+fn gzip(body: &[u8]) -> Option<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+
+    encoder.write_all(body).ok()?;
+    encoder.finish().ok()
+}
+
+impl<'r> Responder<'r> for CachedFeed {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let compressed = if accepts_gzip(request) {
+            gzip(self.body.as_bytes())
+        } else {
+            None
+        };
+        // Compressed and uncompressed representations need distinct tags.
+        let etag = match compressed {
+            Some(_) => format!("\"{}-gzip\"", self.etag),
+            None => format!("\"{}\"", self.etag),
+        };
+
+        let mut response = Response::build();
+        response
+            .raw_header("ETag", etag.clone())
+            .raw_header("Vary", "Accept-Encoding")
+            .raw_header("Cache-Control", "public, max-age=60");
+        if let Some(last_modified) = self.last_modified {
+            response.raw_header("Last-Modified", last_modified.format(HTTP_DATE).to_string());
+        }
+
+        if self.is_not_modified(request, &etag) {
+            debug!("Feed not modified since the last request");
+            return response.status(Status::NotModified).ok();
+        }
+
+        response.header(self.content_type);
+        match compressed {
+            Some(body) => response
+                .raw_header("Content-Encoding", "gzip")
+                .sized_body(Cursor::new(body))
+                .ok(),
+            None => response
+                .sized_body(Cursor::new(self.body))
+                .ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket;
+    use rocket::http::{Header, Status};
+    use rocket::local::Client;
+    use chrono::{TimeZone, Utc};
+    use serde_json;
+    use std::env;
+    use std::fs::{self, File};
+    use std::process;
+    use std::sync::{Arc, Mutex};
+
+    use config::Config;
+    use content;
+    use event_manager::EventManager;
+    use events::{Actor, Event, Forkee, Payload, Repository, Source};
+
+    /// A client for the feed routes with a single fork in the history.
+    fn client(name: &str) -> Client {
+        let dir = env::temp_dir().join(format!("gabeln-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let event = Event {
+            id: "1".to_string(),
+            actor: Actor {
+                display_login: "octocat".to_string(),
+                avatar_url: "https://avatars.githubusercontent.com/u/583231".to_string(),
+            },
+            repo: Repository {
+                name: "rust-lang/rust".to_string(),
+                metadata: None,
+            },
+            payload: Payload::Fork {
+                forkee: Forkee {
+                    id: Some(1),
+                    full_name: "octocat/rust".to_string(),
+                    html_url: "https://github.com/octocat/rust".to_string(),
+                },
+            },
+            created_at: Utc.ymd(2019, 10, 1).and_hms(12, 0, 0),
+            source: Source::User("octocat".to_string()),
+        };
+        let events_file = dir.join("events.json");
+        serde_json::to_writer(File::create(&events_file).unwrap(), &vec![event]).unwrap();
+
+        let mut config = Config::default();
+        config.events_file = events_file.to_string_lossy().into_owned();
+        config.seen_events_file = dir.join("seen-events.jsonl").to_string_lossy().into_owned();
+        config.tracked_users_file = dir.join("tracked-users.json").to_string_lossy().into_owned();
+        let (event_manager, _) = EventManager::new(config.clone()).unwrap();
+
+        let rocket = rocket::ignite()
+            .mount("/", routes![content::feed])
+            .manage(Arc::new(Mutex::new(event_manager)))
+            .manage(config);
+
+        Client::new(rocket).unwrap()
+    }
+
+    #[test]
+    fn not_modified_if_etag_matches() {
+        let client = client("etag");
+
+        let mut response = client.get("/atom.xml").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.body_string().map_or(false, |body| body.contains("octocat/rust")));
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+        assert!(!etag.ends_with("-gzip\""));
+
+        let mut response = client.get("/atom.xml")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+        assert_eq!(response.headers().get_one("ETag"), Some(etag.as_str()));
+        assert!(response.body().is_none());
+
+        let response = client.get("/atom.xml")
+            .header(Header::new("If-None-Match", "\"0000000000000000\""))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn gzip_has_its_own_etag() {
+        let client = client("gzip");
+
+        let response = client.get("/atom.xml")
+            .header(Header::new("Accept-Encoding", "gzip, deflate"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+        assert!(etag.ends_with("-gzip\""));
+
+        let mut response = client.get("/atom.xml")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+        assert!(response.body().is_none());
+
+        // the compressed tag does not validate the uncompressed feed
+        let response = client.get("/atom.xml")
+            .header(Header::new("If-None-Match", etag))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.headers().get_one("Content-Encoding").is_none());
+    }
+
+    #[test]
+    fn not_modified_since_last_modified() {
+        let client = client("modified-since");
+
+        let response = client.get("/atom.xml").dispatch();
+        let last_modified = response.headers().get_one("Last-Modified").unwrap().to_string();
+        assert_eq!(last_modified, "Tue, 01 Oct 2019 12:00:00 GMT");
+
+        let mut response = client.get("/atom.xml")
+            .header(Header::new("If-Modified-Since", last_modified))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+        assert!(response.body().is_none());
+
+        let response = client.get("/atom.xml")
+            .header(Header::new("If-Modified-Since", "Mon, 30 Sep 2019 12:00:00 GMT"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Duration, Utc};
+use serde_json;
+use regex::Regex;
+use telegram_bot_fork::{ChatId, UserId};
+
+use error::GabelnError;
+use events::Event;
+use store::write_json_atomically;
+
+/// Time after which the administrators of a group chat are fetched again.
+const ADMINS_MAX_AGE_MINUTES: i64 = 10;
+
+/// Everything the bot remembers about a chat.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ChatState {
+    /// Whether events are sent to this chat
+    pub active: bool,
+    /// Cached administrators of group chats, `None` if not fetched yet
+    pub admins: Option<Vec<i64>>,
+    /// When the administrators were fetched
+    pub admins_fetched_at: Option<DateTime<Utc>>,
+    /// Logins of the users whose events are sent, everyone's if empty
+    pub following: Vec<String>,
+    /// Regular expression the names of repositories have to match
+    pub repo_filter: Option<String>,
+}
+
+impl ChatState {
+    /// Whether the cached administrators are missing or too old to be
+    /// trusted, as they may have changed in the meantime.
+    pub fn admins_outdated(&self, now: DateTime<Utc>) -> bool {
+        match (&self.admins, self.admins_fetched_at) {
+            (&Some(_), Some(fetched_at)) => now - fetched_at > Duration::minutes(ADMINS_MAX_AGE_MINUTES),
+            _ => true,
+        }
+    }
+
+    /// Whether the event passes the filters of this chat.
+    pub fn matches(&self, event: &Event) -> bool {
+        let followed = self.following.is_empty() || self.following
+            .iter()
+            .any(|login| login.eq_ignore_ascii_case(&event.actor.display_login));
+        let repo_matches = self.repo_filter
+            .as_ref()
+            .and_then(|filter| Regex::new(filter).ok())
+            .map_or(true, |filter| filter.is_match(&event.repo.name));
+
+        followed && repo_matches
+    }
+}
+
+/// Telegram chats the bot knows, keyed by chat id.
+///
+/// The chats are stored as a JSON object and written after every change, so
+/// the bot keeps running in all chats after a restart.
+pub struct ChatStore {
+    path: PathBuf,
+    chats: HashMap<i64, ChatState>,
+}
+
+impl ChatStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, GabelnError> {
+        let path = path.as_ref().to_path_buf();
+        let mut chats = HashMap::new();
+
+        if path.exists() {
+            debug!("Loading telegram chats from {}", path.display());
+
+            let file = File::open(&path)
+                .map_err(|e| {
+                    error!("Failed to open telegram chats: {}", e);
+                    GabelnError::FailedToReadChats
+                })?;
+            chats = serde_json::from_reader(BufReader::new(file))
+                .map_err(|e| {
+                    error!("Failed to parse telegram chats: {}", e);
+                    GabelnError::FailedToReadChats
+                })?;
+
+            info!("Loaded {} telegram chats", chats.len());
+        }
+
+        Ok(Self {
+            path: path,
+            chats: chats,
+        })
+    }
+
+    pub fn get(&self, chat: ChatId) -> Option<&ChatState> {
+        self.chats.get(&i64::from(chat))
+    }
+
+    /// Changes the state of a chat and saves all chats.
+    pub fn update<F: FnOnce(&mut ChatState)>(&mut self, chat: ChatId, update: F) -> Result<(), GabelnError> {
+        update(self.chats.entry(i64::from(chat)).or_insert_with(ChatState::default));
+
+        self.save()
+    }
+
+    /// Forgets a chat and saves all chats.
+    pub fn remove(&mut self, chat: ChatId) -> Result<(), GabelnError> {
+        if self.chats.remove(&i64::from(chat)).is_some() {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_active(&self, chat: ChatId) -> bool {
+        self.get(chat).map_or(false, |state| state.active)
+    }
+
+    /// All active chats whose filters the event passes.
+    pub fn recipients(&self, event: &Event) -> Vec<ChatId> {
+        self.chats
+            .iter()
+            .filter(|&(_, state)| state.active && state.matches(event))
+            .map(|(id, _)| ChatId::new(*id))
+            .collect()
+    }
+
+    /// Whether the user is a cached administrator of the chat.
+    pub fn is_admin(&self, chat: ChatId, user: UserId) -> bool {
+        self.get(chat)
+            .and_then(|state| state.admins.as_ref())
+            .map_or(false, |admins| admins.contains(&i64::from(user)))
+    }
+
+    fn save(&self) -> Result<(), GabelnError> {
+        write_json_atomically(&self.path, &self.chats)
+            .map_err(|e| {
+                error!("Failed to write telegram chats: {}", e);
+                GabelnError::FailedToWriteChats
+            })
+    }
+}
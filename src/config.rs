@@ -0,0 +1,378 @@
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+use chrono::NaiveDate;
+use reqwest::Url;
+use toml;
+
+use error::GabelnError;
+use events::{EventType, Source};
+
+const DEFAULT_CONFIG_FILE: &str = "gabeln.toml";
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Author {
+    pub name: String,
+    pub email: Option<String>,
+    pub uri: Option<String>,
+}
+
+impl Default for Author {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            email: None,
+            uri: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SiteConfig {
+    pub title: String,
+    pub subtitle: String,
+    /// Public URL the site is reachable at, used for absolute links in feeds
+    pub base_url: String,
+    pub language: String,
+    pub authors: Vec<Author>,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        Self {
+            title: "gabeln.jetzt".to_string(),
+            subtitle: "GitHub Fork Feed".to_string(),
+            base_url: "https://gabeln.jetzt".to_string(),
+            language: "en".to_string(),
+            authors: vec![
+                Author {
+                    name: "Fin Christensen".to_string(),
+                    email: Some("fchristensen@embedded.enterprises".to_string()),
+                    uri: Some("https://blog.like-a-fin.lol".to_string()),
+                },
+                Author {
+                    name: "Johannes Wuensche".to_string(),
+                    email: Some("johannes.wuensche@st.ovgu.de".to_string()),
+                    uri: Some("https://github.com/jwuensche".to_string()),
+                },
+            ],
+        }
+    }
+}
+
+impl SiteConfig {
+    /// Absolute URL of the given path on this site.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// A `tag:` URI (RFC 4151) minted by the host of the site on the given
+    /// date.
+    pub fn tag_uri(&self, date: NaiveDate, specific: &str) -> String {
+        let host = Url::parse(&self.base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()))
+            .unwrap_or_else(|| "localhost".to_string());
+
+        format!("tag:{},{}:{}", host, date.format("%Y-%m-%d"), specific)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GiphyConfig {
+    pub api_key: Option<String>,
+    pub search_term: String,
+    pub gif_limit: u32,
+}
+
+impl Default for GiphyConfig {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            search_term: "fork food".to_string(),
+            gif_limit: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelegramConfig {
+    pub bot_token: Option<String>,
+    /// Path of the active chats, their admins and settings
+    pub chats_file: String,
+    /// Telegram user ids allowed to track and untrack GitHub users in any chat
+    pub admins: Vec<i64>,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            bot_token: None,
+            chats_file: "telegram-chats.json".to_string(),
+            admins: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub users: Vec<String>,
+    pub orgs: Vec<String>,
+    /// Repositories given as `owner/name`
+    pub repos: Vec<String>,
+    pub event_types: Vec<String>,
+    /// Seconds between two polls of the GitHub API.
+    pub poll_interval: u32,
+    /// Maximum number of pages fetched per source and poll.
+    pub max_pages: u32,
+    pub github_oauth_token: Option<String>,
+    /// Secret of the GitHub webhook, webhooks are rejected if not set.
+    pub webhook_secret: Option<String>,
+    pub seen_events_file: String,
+    pub events_file: String,
+    /// Path of the users tracked and untracked through the Telegram bot
+    pub tracked_users_file: String,
+    /// Number of days events are kept in the history, forever if not set.
+    pub retention_days: Option<u32>,
+    pub bind_address: String,
+    pub site: SiteConfig,
+    pub giphy: GiphyConfig,
+    pub telegram: TelegramConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            users: Vec::new(),
+            orgs: Vec::new(),
+            repos: Vec::new(),
+            event_types: vec!["ForkEvent".to_string()],
+            poll_interval: 300,
+            max_pages: 3,
+            github_oauth_token: None,
+            webhook_secret: None,
+            seen_events_file: "seen-events.jsonl".to_string(),
+            events_file: "events.json".to_string(),
+            tracked_users_file: "tracked-users.json".to_string(),
+            retention_days: None,
+            bind_address: "0.0.0.0:8000".to_string(),
+            site: SiteConfig::default(),
+            giphy: GiphyConfig::default(),
+            telegram: TelegramConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration file given by `CONFIG_FILE` (or `gabeln.toml`
+    /// if present), applies environment variable overrides and validates the
+    /// result.
+    pub fn load() -> Result<Self, GabelnError> {
+        let mut config = match env::var("CONFIG_FILE") {
+            Ok(path) => Self::from_file(&path)?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::from_file(DEFAULT_CONFIG_FILE)?,
+            Err(_) => {
+                info!("No configuration file found, using defaults and environment variables");
+                Self::default()
+            },
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn from_file(path: &str) -> Result<Self, GabelnError> {
+        info!("Loading configuration from {}", path);
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| {
+                error!("Failed to read configuration file: {}", e);
+                GabelnError::FailedToReadConfig(path.into())
+            })?;
+
+        toml::from_str(&content)
+            .map_err(|e| GabelnError::FailedToParseConfig(path.into(), e.to_string()))
+    }
+
+    fn apply_env(&mut self) -> Result<(), GabelnError> {
+        if let Ok(users) = env::var("USERS") {
+            self.users = split_list(&users);
+        }
+        if let Ok(orgs) = env::var("ORGS") {
+            self.orgs = split_list(&orgs);
+        }
+        if let Ok(repos) = env::var("REPOS") {
+            self.repos = split_list(&repos);
+        }
+        if let Ok(event_types) = env::var("EVENT_TYPES") {
+            self.event_types = split_list(&event_types);
+        }
+        parse_env("POLL_INTERVAL", &mut self.poll_interval)?;
+        parse_env("MAX_PAGES", &mut self.max_pages)?;
+        if let Ok(token) = env::var("GITHUB_OAUTH_TOKEN") {
+            self.github_oauth_token = Some(token);
+        }
+        if let Ok(secret) = env::var("WEBHOOK_SECRET") {
+            self.webhook_secret = Some(secret);
+        }
+        parse_env("SEEN_EVENTS_FILE", &mut self.seen_events_file)?;
+        parse_env("EVENTS_FILE", &mut self.events_file)?;
+        parse_env("TRACKED_USERS_FILE", &mut self.tracked_users_file)?;
+        if env::var("RETENTION_DAYS").is_ok() {
+            let mut retention_days = 0;
+            parse_env("RETENTION_DAYS", &mut retention_days)?;
+            self.retention_days = Some(retention_days);
+        }
+        parse_env("BIND_ADDRESS", &mut self.bind_address)?;
+        parse_env("SITE_TITLE", &mut self.site.title)?;
+        parse_env("SITE_SUBTITLE", &mut self.site.subtitle)?;
+        parse_env("SITE_BASE_URL", &mut self.site.base_url)?;
+        if let Ok(api_key) = env::var("GIPHY_API_KEY") {
+            self.giphy.api_key = Some(api_key);
+        }
+        parse_env("GIPHY_SEARCH_TERM", &mut self.giphy.search_term)?;
+        parse_env("GIPHY_GIF_LIMIT", &mut self.giphy.gif_limit)?;
+        if let Ok(token) = env::var("TELEGRAM_BOT_TOKEN") {
+            self.telegram.bot_token = Some(token);
+        }
+        parse_env("TELEGRAM_CHATS_FILE", &mut self.telegram.chats_file)?;
+        if let Ok(admins) = env::var("TELEGRAM_ADMINS") {
+            self.telegram.admins = split_list(&admins)
+                .iter()
+                .map(|admin| admin
+                    .parse()
+                    .map_err(|_| invalid("TELEGRAM_ADMINS", &format!("`{}` is not a Telegram user id", admin))))
+                .collect::<Result<Vec<i64>, GabelnError>>()?;
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), GabelnError> {
+        if self.users.is_empty() && self.orgs.is_empty() && self.repos.is_empty() {
+            return Err(invalid("users", "at least one GitHub user, organization or repository has to be configured"));
+        }
+        if let Some(user) = self.users.iter().find(|user| user.is_empty() || user.contains('/')) {
+            return Err(invalid("users", &format!("`{}` is not a valid GitHub login", user)));
+        }
+        if let Some(org) = self.orgs.iter().find(|org| org.is_empty() || org.contains('/')) {
+            return Err(invalid("orgs", &format!("`{}` is not a valid GitHub organization", org)));
+        }
+        if let Some(repo) = self.repos.iter().find(|repo| {
+            let parts = repo.split('/').collect::<Vec<&str>>();
+            parts.len() != 2 || parts.iter().any(|part| part.is_empty())
+        }) {
+            return Err(invalid("repos", &format!("`{}` is not of the form `owner/name`", repo)));
+        }
+        if let Some(name) = self.event_types.iter().find(|name| EventType::from_github_name(name).is_none()) {
+            return Err(invalid("event_types", &format!("unsupported event type `{}`", name)));
+        }
+        if self.event_types.is_empty() {
+            return Err(invalid("event_types", "at least one event type has to be enabled"));
+        }
+        if self.poll_interval < 60 {
+            return Err(invalid("poll_interval", "has to be at least 60 seconds"));
+        }
+        if self.max_pages == 0 {
+            return Err(invalid("max_pages", "has to be at least 1"));
+        }
+        if self.github_oauth_token.is_none() {
+            warn!("No GitHub OAuth token configured! You will run into GitHub rate limiting!");
+        }
+        if self.webhook_secret.as_ref().map_or(false, |secret| secret.is_empty()) {
+            return Err(invalid("webhook_secret", "must not be empty"));
+        }
+        if self.seen_events_file.is_empty() {
+            return Err(invalid("seen_events_file", "must not be empty"));
+        }
+        if self.events_file.is_empty() {
+            return Err(invalid("events_file", "must not be empty"));
+        }
+        if self.tracked_users_file.is_empty() {
+            return Err(invalid("tracked_users_file", "must not be empty"));
+        }
+        if self.retention_days == Some(0) {
+            return Err(invalid("retention_days", "has to be at least 1 day"));
+        }
+        self.socket_addr()?;
+        if self.site.title.is_empty() {
+            return Err(invalid("site.title", "must not be empty"));
+        }
+        match Url::parse(&self.site.base_url) {
+            Ok(ref url) if (url.scheme() == "http" || url.scheme() == "https")
+                && url.host_str().is_some()
+                && url.query().is_none()
+                && url.fragment().is_none() => (),
+            _ => return Err(invalid("site.base_url", &format!("`{}` is not an absolute http(s) URL", self.site.base_url))),
+        }
+        if self.site.language.is_empty() {
+            return Err(invalid("site.language", "must not be empty"));
+        }
+        if self.site.authors.iter().any(|author| author.name.is_empty()) {
+            return Err(invalid("site.authors", "every author needs a name"));
+        }
+        if self.telegram.chats_file.is_empty() {
+            return Err(invalid("telegram.chats_file", "must not be empty"));
+        }
+        if self.giphy.search_term.is_empty() {
+            return Err(invalid("giphy.search_term", "must not be empty"));
+        }
+        if self.giphy.gif_limit == 0 {
+            return Err(invalid("giphy.gif_limit", "has to be greater than 0"));
+        }
+
+        Ok(())
+    }
+
+    pub fn socket_addr(&self) -> Result<SocketAddr, GabelnError> {
+        self.bind_address
+            .parse()
+            .map_err(|_| invalid("bind_address", &format!("`{}` is not a valid socket address", self.bind_address)))
+    }
+
+    pub fn sources(&self) -> Vec<Source> {
+        self.users.iter().map(|user| Source::User(user.clone()))
+            .chain(self.orgs.iter().map(|org| Source::Org(org.clone())))
+            .chain(self.repos.iter().map(|repo| Source::Repo(repo.clone())))
+            .collect()
+    }
+
+    pub fn event_types(&self) -> Vec<EventType> {
+        self.event_types
+            .iter()
+            .filter_map(|name| EventType::from_github_name(name))
+            .collect()
+    }
+}
+
+fn invalid(key: &str, reason: &str) -> GabelnError {
+    GabelnError::InvalidConfig(key.into(), reason.into())
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(",")
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+        .collect()
+}
+
+fn parse_env<T: FromStr>(name: &str, target: &mut T) -> Result<(), GabelnError> {
+    if let Ok(value) = env::var(name) {
+        *target = value
+            .parse()
+            .map_err(|_| invalid(name, &format!("`{}` could not be parsed", value)))?;
+    }
+
+    Ok(())
+}
@@ -1,34 +1,49 @@
-use rocket::{State, Request, response::content};
+use rocket::{State, Request, response::content, request::LenientForm};
+use rocket::http::ContentType;
 use maud::{html, DOCTYPE, Markup};
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 use chrono_humanize::HumanTime;
+use cache::CachedFeed;
 use event_manager::EventManager;
+use events::{Event, Payload, RepositoryMetadata};
+use config::{Config, SiteConfig};
+use feed;
+use query::EventQuery;
+use serde_json;
+use stats;
 use std::sync::{Arc, Mutex};
 
-pub fn gabeln(title: &str, content: Markup) -> content::Html<String> {
+pub fn gabeln(site: &SiteConfig, title: &str, content: Markup) -> content::Html<String> {
     content::Html((html! {
         (DOCTYPE)
-        html lang="en" {
+        html lang=(site.language) {
             head {
                 meta charset="utf-8";
                 meta name="viewport" content="width=device-width, initial-scale=1.0";
                 title { (title) }
-                link rel="stylesheet" type="text/css" href="semantic.min.css";
+                link rel="stylesheet" type="text/css" href="/semantic.min.css";
+                link rel="alternate" type="application/atom+xml" title=(format!("{} (Atom)", site.title)) href="/atom.xml";
+                link rel="alternate" type="application/rss+xml" title=(format!("{} (RSS)", site.title)) href="/rss.xml";
+                link rel="alternate" type="application/feed+json" title=(format!("{} (JSON Feed)", site.title)) href="/feed.json";
             }
 
             body {
                 div.ui.stackable.menu.borderless {
                     div.ui.text.container {
-                        div.header.item { "gabeln.jetzt" }
+                        div.header.item { (site.title) }
                         a.item href="/" { "Home" }
                         a.item href="/atom.xml" { "Atom Feed" }
+                        a.item href="/leaderboard" { "Leaderboard" }
+                        a.item href="/stats" { "Statistics" }
+                        a.item href="/status" { "Status" }
                         a.item href="/about" { "About" }
                     }
                 }
                 div.ui.text.container {
                     (content)
                     p.ui.basic.padded.center.aligned.segment {
-                        "gabeln.jetzt is powered by "
+                        (site.title)
+                        " is powered by "
                         a href="https://rocket.rs" { "rocket" }
                         " science, "
                         a href="https://semantic-ui.com" { "Semantic UI" }
@@ -45,9 +60,10 @@ pub fn gabeln(title: &str, content: Markup) -> content::Html<String> {
 }
 
 #[catch(404)]
-pub fn not_found(_req: &Request) -> content::Html<String> {
+pub fn not_found(req: &Request) -> content::Html<String> {
     debug!("Handling 404 request");
-    gabeln("Not found", html! {
+    let config = req.guard::<State<Config>>().unwrap();
+    gabeln(&config.site, "Not found", html! {
         div.ui.placeholder.segment {
             div.ui.icon.header {
                 i.minus.circle.icon style="margin: 0.25em" { }
@@ -59,12 +75,30 @@ pub fn not_found(_req: &Request) -> content::Html<String> {
     })
 }
 
-#[get("/")]
-pub fn index(event_manager: State<Arc<Mutex<EventManager>>>) -> content::Html<String> {
-    debug!("Handling / request");
-    gabeln("gabeln.jetzt", html! {
+fn repository_metadata(metadata: &RepositoryMetadata) -> Markup {
+    html! {
+        @if let Some(ref description) = metadata.description {
+            div.extra.text { (description) }
+        }
+        div.meta {
+            @if let Some(ref language) = metadata.language {
+                span { i.code.icon {} (language) }
+            }
+            span { i.star.icon {} (metadata.stargazers_count) }
+            @if let Some(ref license) = metadata.license {
+                span { i.balance.scale.icon {} (license) }
+            }
+            @for topic in metadata.topics.iter() {
+                span.ui.mini.basic.label { (topic) }
+            }
+        }
+    }
+}
+
+fn events_feed(events: Vec<&Event>) -> Markup {
+    html! {
         div.ui.feed {
-            @for ref event in event_manager.inner().lock().unwrap().events.iter().rev() {
+            @for event in events {
                 div.event {
                     div.label {
                         a href=(format!("https://github.com/{}", event.actor.display_login)) {
@@ -76,34 +110,449 @@ pub fn index(event_manager: State<Arc<Mutex<EventManager>>>) -> content::Html<St
                             (HumanTime::from(event.created_at - Utc::now()))
                         }
                         div.summary {
-                            (event.actor.display_login)
-                            " forked "
-                            a href=(format!("https://github.com/{}", event.repo.name)) {
-                                (event.repo.name)
+                            a href=(format!("/user/{}", event.actor.display_login)) {
+                                (event.actor.display_login)
                             }
-                            " at "
-                            a href=(event.payload.forkee.clone().unwrap().html_url) {
-                                (event.payload.forkee.clone().unwrap().full_name)
+                            @match event.payload {
+                                Payload::Fork { ref forkee } => {
+                                    " forked "
+                                    a href=(format!("/repo/{}", event.repo.name)) {
+                                        (event.repo.name)
+                                    }
+                                    " at "
+                                    a href=(forkee.html_url) {
+                                        (forkee.full_name)
+                                    }
+                                    "!"
+                                },
+                                Payload::Watch => {
+                                    " starred "
+                                    a href=(format!("https://github.com/{}", event.repo.name)) {
+                                        (event.repo.name)
+                                    }
+                                    "!"
+                                },
+                                Payload::Create { ref description } => {
+                                    " created the repository "
+                                    a href=(format!("https://github.com/{}", event.repo.name)) {
+                                        (event.repo.name)
+                                    }
+                                    "!"
+                                    @if event.repo.metadata.is_none() {
+                                        @if let Some(ref description) = *description {
+                                            div.extra.text { (description) }
+                                        }
+                                    }
+                                },
+                                Payload::Release { ref release } => {
+                                    " released "
+                                    a href=(release.html_url) {
+                                        (release.tag_name)
+                                    }
+                                    " of "
+                                    a href=(format!("https://github.com/{}", event.repo.name)) {
+                                        (event.repo.name)
+                                    }
+                                    "!"
+                                },
+                                Payload::Public => {
+                                    " made "
+                                    a href=(format!("https://github.com/{}", event.repo.name)) {
+                                        (event.repo.name)
+                                    }
+                                    " public!"
+                                },
                             }
-                            "!"
+                        }
+                        @if let Some(ref metadata) = event.repo.metadata {
+                            (repository_metadata(metadata))
                         }
                     }
                 }
             }
         }
+    }
+}
+
+fn filter_form(query: &EventQuery) -> Markup {
+    html! {
+        form.ui.form method="get" action="/" {
+            div.fields {
+                div.four.wide.field {
+                    label { "User" }
+                    input type="text" name="user" placeholder="fin-ger" value=(query.user().unwrap_or(""));
+                }
+                div.four.wide.field {
+                    label { "Owner" }
+                    input type="text" name="owner" placeholder="rust-lang" value=(query.owner().unwrap_or(""));
+                }
+                div.eight.wide.field {
+                    label { "Repository" }
+                    input type="text" name="q" placeholder="Search repositories and users" value=(query.q().unwrap_or(""));
+                }
+            }
+            div.two.fields {
+                div.field {
+                    label { "Since" }
+                    input type="date" name="since" value=(query.since().map(|date| date.to_string()).unwrap_or_default());
+                }
+                div.field {
+                    label { "Until" }
+                    input type="date" name="until" value=(query.until().map(|date| date.to_string()).unwrap_or_default());
+                }
+            }
+            button.ui.primary.button type="submit" { "Filter" }
+            a.ui.button href="/" { "Reset" }
+            @if query.is_filtered() {
+                a.ui.right.floated.basic.button href=(query.url("/atom.xml", 1, None, None)) {
+                    i.rss.icon { }
+                    "Subscribe to this selection"
+                }
+            }
+        }
+        div.ui.hidden.divider { }
+    }
+}
+
+fn pagination(label: String, newer: Option<String>, older: Option<String>) -> Markup {
+    html! {
+        div.ui.borderless.fluid.three.item.menu {
+            @match newer {
+                Some(ref url) => { a.item href=(url) { i.left.chevron.icon { } "Newer" } },
+                None => { div.disabled.item { i.left.chevron.icon { } "Newer" } },
+            }
+            div.item { (label) }
+            @match older {
+                Some(ref url) => { a.item href=(url) { "Older" i.right.chevron.icon { } } },
+                None => { div.disabled.item { "Older" i.right.chevron.icon { } } },
+            }
+        }
+    }
+}
+
+#[get("/?<query..>")]
+pub fn index(
+    query: LenientForm<EventQuery>,
+    event_manager: State<Arc<Mutex<EventManager>>>,
+    config: State<Config>,
+) -> content::Html<String> {
+    debug!("Handling / request");
+    let event_manager = event_manager.inner().lock().unwrap();
+    let page = query.page();
+    let page_events = query.paginate("/", &event_manager.events);
+    let label = match (query.after(), query.before()) {
+        (Some(after), _) if page == 1 => format!("After {}", after.created_at.format("%Y-%m-%d %H:%M")),
+        (None, Some(before)) if page == 1 => format!("Before {}", before.created_at.format("%Y-%m-%d %H:%M")),
+        _ => format!("Page {}", page),
+    };
+
+    gabeln(&config.site, &config.site.title, html! {
+        (filter_form(&query))
+        (events_feed(page_events.events))
+        (pagination(label, page_events.newer, page_events.older))
+    })
+}
+
+#[get("/user/<login>")]
+pub fn user(login: String, event_manager: State<Arc<Mutex<EventManager>>>, config: State<Config>) -> content::Html<String> {
+    debug!("Handling /user/{} request", login);
+    let event_manager = event_manager.inner().lock().unwrap();
+    let events = event_manager.events
+        .iter()
+        .rev()
+        .filter(|event| stats::is_fork(event) && event.actor.display_login.eq_ignore_ascii_case(&login))
+        .collect::<Vec<&Event>>();
+
+    gabeln(&config.site, &login, html! {
+        h2.ui.header {
+            img.ui.circular.image src=(format!("https://github.com/{}.png", login));
+            div.content {
+                a href=(format!("https://github.com/{}", login)) { (login) }
+                div.sub.header { (stats::fork_count(events.len())) }
+            }
+        }
+        (events_feed(events))
     })
 }
 
-#[get("/atom.xml")]
-pub fn feed(event_manager: State<Arc<Mutex<EventManager>>>) -> content::Xml<String> {
+#[get("/repo/<owner>/<name>")]
+pub fn repo(owner: String, name: String, event_manager: State<Arc<Mutex<EventManager>>>, config: State<Config>) -> content::Html<String> {
+    let full_name = format!("{}/{}", owner, name);
+    debug!("Handling /repo/{} request", full_name);
+    let event_manager = event_manager.inner().lock().unwrap();
+    let events = event_manager.events
+        .iter()
+        .rev()
+        .filter(|event| stats::is_fork(event) && event.repo.name.eq_ignore_ascii_case(&full_name))
+        .collect::<Vec<&Event>>();
+
+    gabeln(&config.site, &full_name, html! {
+        h2.ui.header {
+            img.ui.circular.image src=(format!("https://github.com/{}.png", owner));
+            div.content {
+                a href=(format!("https://github.com/{}", full_name)) { (full_name) }
+                div.sub.header { (stats::fork_count(events.len())) }
+            }
+        }
+        (events_feed(events))
+    })
+}
+
+/// Events matching the filters of a feed request, `None` if unfiltered.
+fn feed_events(query: &EventQuery, event_manager: &EventManager) -> Option<Vec<Event>> {
+    if !query.is_filtered() {
+        return None;
+    }
+
+    Some(
+        event_manager.events
+            .iter()
+            .filter(|event| query.matches(event))
+            .cloned()
+            .collect()
+    )
+}
+
+#[get("/atom.xml?<query..>")]
+pub fn feed(
+    query: LenientForm<EventQuery>,
+    event_manager: State<Arc<Mutex<EventManager>>>,
+    config: State<Config>,
+) -> Option<CachedFeed> {
     debug!("Handling /atom.xml request");
-    content::Xml(event_manager.inner().lock().unwrap().feed.to_string())
+    let event_manager = event_manager.inner().lock().unwrap();
+    let content_type = ContentType::new("application", "atom+xml");
+
+    match feed_events(&query, &event_manager) {
+        Some(events) => feed::create_feed(&events, &config.site)
+            .map(|feed| CachedFeed::new(content_type, feed.to_string(), &events))
+            .map_err(|e| error!("{:?}", e))
+            .ok(),
+        None => Some(CachedFeed::new(content_type, event_manager.feed.to_string(), &event_manager.events)),
+    }
+}
+
+#[get("/rss.xml?<query..>")]
+pub fn rss(
+    query: LenientForm<EventQuery>,
+    event_manager: State<Arc<Mutex<EventManager>>>,
+    config: State<Config>,
+) -> Option<CachedFeed> {
+    debug!("Handling /rss.xml request");
+    let event_manager = event_manager.inner().lock().unwrap();
+    let content_type = ContentType::new("application", "rss+xml");
+
+    match feed_events(&query, &event_manager) {
+        Some(events) => feed::create_rss(&events, &config.site)
+            .map(|channel| CachedFeed::new(content_type, channel.to_string(), &events))
+            .map_err(|e| error!("{:?}", e))
+            .ok(),
+        None => Some(CachedFeed::new(content_type, event_manager.rss.to_string(), &event_manager.events)),
+    }
+}
+
+#[get("/feed.json?<query..>")]
+pub fn json_feed(
+    query: LenientForm<EventQuery>,
+    event_manager: State<Arc<Mutex<EventManager>>>,
+    config: State<Config>,
+) -> Option<CachedFeed> {
+    debug!("Handling /feed.json request");
+    let event_manager = event_manager.inner().lock().unwrap();
+    let content_type = ContentType::new("application", "feed+json");
+
+    match feed_events(&query, &event_manager) {
+        Some(events) => serde_json::to_string(&feed::create_json_feed(&events, &config.site))
+            .map(|json| CachedFeed::new(content_type, json, &events))
+            .map_err(|e| error!("{:?}", e))
+            .ok(),
+        None => Some(CachedFeed::new(content_type, event_manager.json_feed.to_string(), &event_manager.events)),
+    }
+}
+
+const CHART_WIDTH: f64 = 600.0;
+const CHART_HEIGHT: f64 = 200.0;
+
+/// A bar chart of forks per week as inline SVG.
+fn fork_chart(weeks: &[(NaiveDate, usize)]) -> Markup {
+    let max = weeks.iter().map(|&(_, count)| count).max().unwrap_or(0).max(1);
+    let bar_width = CHART_WIDTH / weeks.len().max(1) as f64;
+
+    html! {
+        svg width="100%" viewBox=(format!("0 0 {} {}", CHART_WIDTH, CHART_HEIGHT + 20.0)) role="img" {
+            title { "Forks per week" }
+            line x1="0" y1=(CHART_HEIGHT) x2=(CHART_WIDTH) y2=(CHART_HEIGHT) stroke="#999" {}
+            @for (i, &(week, count)) in weeks.iter().enumerate() {
+                @let height = CHART_HEIGHT * count as f64 / max as f64;
+                rect
+                    x=(format!("{:.2}", i as f64 * bar_width))
+                    y=(format!("{:.2}", CHART_HEIGHT - height))
+                    width=(format!("{:.2}", (bar_width - 1.0).max(1.0)))
+                    height=(format!("{:.2}", height))
+                    fill="#2185d0" {
+                    title { "Week of " (week.format("%Y-%m-%d")) ": " (stats::fork_count(count)) }
+                }
+            }
+            @if let (Some(&(first, _)), Some(&(last, _))) = (weeks.first(), weeks.last()) {
+                text x="0" y=(CHART_HEIGHT + 15.0) font-size="12" { (first.format("%Y-%m-%d")) }
+                text x=(CHART_WIDTH) y=(CHART_HEIGHT + 15.0) font-size="12" text-anchor="end" { (last.format("%Y-%m-%d")) }
+            }
+            text x="2" y="12" font-size="12" { "max. " (max) }
+        }
+    }
+}
+
+/// A two column table of the ten highest counts.
+fn ranking(title: &str, column: &str, link: &str, counts: Vec<(String, usize)>) -> Markup {
+    html! {
+        h2 { (title) }
+        table.ui.celled.unstackable.table {
+            thead {
+                tr {
+                    th { (column) }
+                    th { "Forks" }
+                }
+            }
+            tbody {
+                @for (name, count) in counts.into_iter().take(10) {
+                    tr {
+                        td { a href=(format!("{}/{}", link, name)) { (name) } }
+                        td { (count) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[get("/stats")]
+pub fn stats(event_manager: State<Arc<Mutex<EventManager>>>, config: State<Config>) -> content::Html<String> {
+    debug!("Handling /stats request");
+    let event_manager = event_manager.inner().lock().unwrap();
+    let events = &event_manager.events;
+
+    gabeln(&config.site, "Statistics", html! {
+        h1 { "Statistics" }
+        @if stats::forks(events).next().is_none() {
+            p { "No forks yet." }
+        } @else {
+            div.ui.two.statistics {
+                div.statistic {
+                    div.value { (stats::forks(events).count()) }
+                    div.label { "Forks" }
+                }
+                @if let Some((day, count)) = stats::busiest_day(events) {
+                    div.statistic {
+                        div.value { (count) }
+                        div.label { "Busiest day: " (day.format("%Y-%m-%d")) }
+                    }
+                }
+            }
+            h2 { "Forks per week" }
+            (fork_chart(&stats::forks_per_week(events)))
+            (ranking("Forks per user", "User", "/user", stats::forks_per_user(events)))
+            (ranking("Most forked repositories", "Repository", "/repo", stats::forks_per_repository(events)))
+            h2 { "Longest streaks" }
+            table.ui.celled.unstackable.table {
+                thead {
+                    tr {
+                        th { "User" }
+                        th { "Days in a row" }
+                        th { "From" }
+                        th { "To" }
+                    }
+                }
+                tbody {
+                    @for streak in stats::longest_streaks(events).into_iter().take(10) {
+                        tr {
+                            td { a href=(format!("/user/{}", streak.login)) { (streak.login) } }
+                            td { (streak.days()) }
+                            td { (streak.start.format("%Y-%m-%d")) }
+                            td { (streak.end.format("%Y-%m-%d")) }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[get("/leaderboard")]
+pub fn leaderboard(event_manager: State<Arc<Mutex<EventManager>>>, config: State<Config>) -> content::Html<String> {
+    debug!("Handling /leaderboard request");
+    let event_manager = event_manager.inner().lock().unwrap();
+
+    gabeln(&config.site, "Leaderboard", html! {
+        h1 { "Leaderboard" }
+        @for window in stats::Window::all() {
+            h2 { (window.label()) }
+            @let ranking = stats::leaderboard(&event_manager.events, event_manager.users(), window);
+            @if ranking.is_empty() {
+                p { "No forks yet." }
+            } @else {
+                table.ui.celled.unstackable.table {
+                    thead {
+                        tr {
+                            th { "#" }
+                            th { "User" }
+                            th { "Forks" }
+                        }
+                    }
+                    tbody {
+                        @for (rank, (login, count)) in ranking.into_iter().take(10).enumerate() {
+                            tr {
+                                td { (rank + 1) }
+                                td { a href=(format!("/user/{}", login)) { (login) } }
+                                td { (count) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[get("/status")]
+pub fn status(event_manager: State<Arc<Mutex<EventManager>>>, config: State<Config>) -> content::Html<String> {
+    debug!("Handling /status request");
+    gabeln(&config.site, "Status", html! {
+        h1 { "Status" }
+        table.ui.celled.table {
+            thead {
+                tr {
+                    th { "Source" }
+                    th { "Last successful poll" }
+                    th { "Health" }
+                }
+            }
+            tbody {
+                @for ref status in event_manager.inner().lock().unwrap().status.iter() {
+                    tr class=(if status.stale { "negative" } else { "" }) {
+                        td { (status.source) }
+                        td {
+                            @match status.last_success {
+                                Some(last_success) => { (HumanTime::from(last_success - Utc::now())) },
+                                None => { "never" },
+                            }
+                        }
+                        td {
+                            @match status.last_error {
+                                Some(ref error) => { "stale: " (error) },
+                                None => { "ok" },
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
 }
 
 #[get("/about")]
-pub fn about() -> content::Html<String> {
+pub fn about(config: State<Config>) -> content::Html<String> {
     debug!("Handling /about request");
-    gabeln("About", html! {
+    gabeln(&config.site, "About", html! {
         h1 { "About" }
         p {
             "This site contains a feed for forks of github repositories by a couple"
@@ -2,8 +2,10 @@ use std::error::Error;
 
 #[derive(Debug, Clone)]
 pub enum GabelnError {
-    FailedToFetchUserEvents(String),
-    FailedToParseUserEvents,
+    FailedToFetchEvents(String),
+    FailedToParseEvents,
+    FailedToFetchRepository(String),
+    RateLimited(String),
     FailedToCreateFeed,
     NoTelegramBotToken,
     FailedToCreateTelegramBot,
@@ -14,25 +16,51 @@ pub enum GabelnError {
     NoGiphyApiKey,
     FailedToFetchGif,
     FailedToParseGiphyResponse,
-    FailedToStartBot,
+    FailedToReadSeenEvents,
+    FailedToWriteSeenEvents,
+    FailedToReadEventStore,
+    FailedToWriteEventStore,
+    FailedToReadChats,
+    FailedToWriteChats,
+    FailedToReadTrackedUsers,
+    FailedToWriteTrackedUsers,
+    FailedToFetchUser(String),
+    UnknownGitHubUser(String),
+    FailedToReadConfig(String),
+    FailedToParseConfig(String, String),
+    InvalidConfig(String, String),
 }
 
 impl Error for GabelnError {
     fn description(&self) -> &str {
         match *self {
-            GabelnError::FailedToFetchUserEvents(_) => "Failed to fetch the events for the given user!",
-            GabelnError::FailedToParseUserEvents => "Failed to parse the user events response body!",
-            GabelnError::FailedToCreateFeed => "Failed to create atom feed from user events!",
-            GabelnError::NoTelegramBotToken => "Please provide a telegram bot token via environment variable!",
+            GabelnError::FailedToFetchEvents(_) => "Failed to fetch the events of the given source!",
+            GabelnError::FailedToParseEvents => "Failed to parse the events response body!",
+            GabelnError::FailedToFetchRepository(_) => "Failed to fetch the metadata of a repository!",
+            GabelnError::RateLimited(_) => "The GitHub API rate limit is exceeded!",
+            GabelnError::FailedToCreateFeed => "Failed to create feed from events!",
+            GabelnError::NoTelegramBotToken => "Please provide a telegram bot token via configuration or environment variable!",
             GabelnError::FailedToCreateTelegramBot => "Could not create Telegram API instance!",
             GabelnError::FailedToListenForTelegramMessages => "Could not listen for Telegram messages!",
             GabelnError::FailedToListenForEvents => "Could not listen for events!",
             GabelnError::FailedToPublishEvents => "Failed to publish user events to channel!",
             GabelnError::FailedToGetOwnUser => "Failed to get user of this bot!",
-            GabelnError::NoGiphyApiKey => "Please provide a giphy API key via environment variable!",
+            GabelnError::NoGiphyApiKey => "Please provide a giphy API key via configuration or environment variable!",
             GabelnError::FailedToFetchGif => "Failed to fetch gif from giphy API!",
             GabelnError::FailedToParseGiphyResponse => "Failed to parse response from giphy API!",
-            GabelnError::FailedToStartBot => "Failed to start bot!",
+            GabelnError::FailedToReadSeenEvents => "Failed to read the log of already published events!",
+            GabelnError::FailedToWriteSeenEvents => "Failed to write to the log of already published events!",
+            GabelnError::FailedToReadEventStore => "Failed to read the event history!",
+            GabelnError::FailedToWriteEventStore => "Failed to write the event history!",
+            GabelnError::FailedToReadChats => "Failed to read the telegram chats!",
+            GabelnError::FailedToWriteChats => "Failed to write the telegram chats!",
+            GabelnError::FailedToReadTrackedUsers => "Failed to read the tracked users!",
+            GabelnError::FailedToWriteTrackedUsers => "Failed to write the tracked users!",
+            GabelnError::FailedToFetchUser(_) => "Failed to look up a GitHub user!",
+            GabelnError::UnknownGitHubUser(_) => "There is no such GitHub user!",
+            GabelnError::FailedToReadConfig(_) => "Failed to read the configuration file!",
+            GabelnError::FailedToParseConfig(..) => "Failed to parse the configuration file!",
+            GabelnError::InvalidConfig(..) => "Invalid configuration value!",
         }
     }
 }
@@ -40,8 +68,29 @@ impl Error for GabelnError {
 impl std::fmt::Display for GabelnError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match *self {
-            GabelnError::FailedToFetchUserEvents(ref user) => write!(
-                f, "Failed to fetch the events for the user {}!", user
+            GabelnError::FailedToFetchEvents(ref source) => write!(
+                f, "Failed to fetch the events of {}!", source
+            ),
+            GabelnError::FailedToFetchRepository(ref repository) => write!(
+                f, "Failed to fetch the metadata of {}!", repository
+            ),
+            GabelnError::FailedToFetchUser(ref login) => write!(
+                f, "Failed to look up the GitHub user {}!", login
+            ),
+            GabelnError::UnknownGitHubUser(ref login) => write!(
+                f, "There is no GitHub user {}!", login
+            ),
+            GabelnError::RateLimited(ref reset) => write!(
+                f, "The GitHub API rate limit is exceeded until {}!", reset
+            ),
+            GabelnError::FailedToReadConfig(ref path) => write!(
+                f, "Failed to read the configuration file {}!", path
+            ),
+            GabelnError::FailedToParseConfig(ref path, ref reason) => write!(
+                f, "Failed to parse the configuration file {}: {}", path, reason
+            ),
+            GabelnError::InvalidConfig(ref key, ref reason) => write!(
+                f, "Invalid configuration value for `{}`: {}", key, reason
             ),
             _ => write!(f, "{}", self.description()),
         }
@@ -1,55 +1,192 @@
-use events::{Event, EventCollector};
+use events::{Event, EventCollector, Source, SourceStatus, GITHUB_API_URL};
 use error::GabelnError;
+use config::Config;
 use feed;
-use std::env;
-use futures::{Future, Sink};
-use futures::sync::mpsc::{Sender, Receiver, channel};
-use chrono::{Utc};
+use serde_json;
+use seen::SeenEvents;
+use store::EventStore;
+use tracked::TrackedUsers;
+use futures::sync::mpsc::{UnboundedSender, UnboundedReceiver, unbounded};
+use std::sync::{Arc, Mutex};
 
 pub struct EventManager {
     pub events: Vec<Event>,
     pub feed: String,
-    sender: Sender<Event>,
+    pub rss: String,
+    pub json_feed: String,
+    pub status: Vec<SourceStatus>,
+    sender: UnboundedSender<Event>,
+    seen: SeenEvents,
+    store: EventStore,
+    collector: Arc<EventCollector>,
+    tracked: TrackedUsers,
+    config: Config,
 }
 
 impl EventManager {
-    pub fn new() -> (Self, Receiver<Event>) {
-        let events = EventCollector::default().collect().unwrap();
-        let (sender, recv) = channel(100);
+    pub fn new(mut config: Config) -> Result<(Self, UnboundedReceiver<Event>), GabelnError> {
+        let seen = SeenEvents::open(&config.seen_events_file)?;
+        let store = EventStore::open(&config.events_file)?;
+        let tracked = TrackedUsers::open(&config.tracked_users_file)?;
+        config.users = tracked.apply(&config.users);
+        let events = store.events();
+        let collector = EventCollector::new(GITHUB_API_URL, config.github_oauth_token.clone())
+            .add_sources(config.sources())
+            .max_pages(config.max_pages)
+            .event_types(config.event_types())
+            .known_events(&events);
+        // Unbounded, as the bot locks the event manager while it handles
+        // commands and a full channel would block `ingest` forever.
+        let (sender, recv) = unbounded();
 
-        (
-            Self {
-                feed: feed::create_feed(&events).unwrap().to_string(),
-                events: events,
-                sender: sender,
-            },
-            recv,
-        )
+        let mut event_manager = Self {
+            events: events,
+            feed: String::new(),
+            rss: String::new(),
+            json_feed: String::new(),
+            status: collector.status(),
+            sender: sender,
+            seen: seen,
+            store: store,
+            collector: Arc::new(collector),
+            tracked: tracked,
+            config: config,
+        };
+        event_manager.render_feeds()?;
+
+        Ok((event_manager, recv))
+    }
+
+    fn render_feeds(&mut self) -> Result<(), GabelnError> {
+        self.feed = feed::create_feed(&self.events, &self.config.site)?.to_string();
+        self.rss = feed::create_rss(&self.events, &self.config.site)?.to_string();
+        self.json_feed = serde_json::to_string(&feed::create_json_feed(&self.events, &self.config.site))
+            .map_err(|_| GabelnError::FailedToCreateFeed)?;
+
+        Ok(())
     }
 
-    pub fn update(&mut self) -> Result<(), GabelnError> {
+    /// Polls GitHub without holding the lock, so the website and the bot
+    /// stay responsive, and only locks the event manager to store the result.
+    pub fn update(event_manager: &Mutex<EventManager>) -> Result<(), GabelnError> {
         info!("Updating event list");
-        self.events = EventCollector::default()
-            .add_users(
-                env::var("USERS")
-                    .unwrap_or("fin-ger,jwuensche".to_string())
-                    .split(",")
-                    .collect::<Vec<&str>>()
-            )
-            .collect()?;
-        self.feed = feed::create_feed(&self.events)?.to_string();
-
-        let now = Utc::now();
-        let duration = chrono::Duration::minutes(5);
-        for event in self.events.iter() {
-            if now - event.created_at < duration {
-                info!("Publishing new fork event: {}", event.payload.forkee.clone().unwrap().full_name);
-                self.sender
-                    .clone()
-                    .send(event.clone())
-                    .wait()
-                    .map_err(|_| GabelnError::FailedToPublishEvents)?;
+        let collector = event_manager.lock().unwrap().collector.clone();
+        let mut collected = collector.collect();
+
+        // stored events keep the metadata they were stored with
+        let new = {
+            let event_manager = event_manager.lock().unwrap();
+
+            collected
+                .iter_mut()
+                .filter(|event| !event_manager.store.contains(event))
+                .collect::<Vec<&mut Event>>()
+        };
+        collector.enrich(new);
+
+        let mut event_manager = event_manager.lock().unwrap();
+        event_manager.status = collector.status();
+        event_manager.ingest(collected)
+    }
+
+    /// Starts tracking a GitHub user with the next update. Returns the login
+    /// as known to GitHub, or `None` if the user is tracked already.
+    ///
+    /// The user is looked up on GitHub without holding the lock.
+    pub fn track(event_manager: &Mutex<EventManager>, login: &str) -> Result<Option<String>, GabelnError> {
+        let collector = {
+            let event_manager = event_manager.lock().unwrap();
+
+            if event_manager.is_tracked(login) {
+                return Ok(None);
+            }
+
+            event_manager.collector.clone()
+        };
+
+        let login = collector
+            .lookup_user(login)?
+            .ok_or_else(|| GabelnError::UnknownGitHubUser(login.to_string()))?;
+
+        let mut event_manager = event_manager.lock().unwrap();
+        if event_manager.is_tracked(&login) {
+            return Ok(None);
+        }
+
+        info!("Tracking user {}", login);
+        event_manager.tracked.track(&login)?;
+        event_manager.config.users.push(login.clone());
+        event_manager.collector.add_source(Source::User(login.clone()));
+        event_manager.status = event_manager.collector.status();
+
+        Ok(Some(login))
+    }
+
+    fn is_tracked(&self, login: &str) -> bool {
+        self.config.users.iter().any(|user| user.eq_ignore_ascii_case(login))
+    }
+
+    /// Stops tracking a GitHub user. Returns the removed login, or `None` if
+    /// the user was not tracked.
+    pub fn untrack(&mut self, login: &str) -> Result<Option<String>, GabelnError> {
+        let login = match self.config.users.iter().find(|user| user.eq_ignore_ascii_case(login)) {
+            Some(user) => user.clone(),
+            None => return Ok(None),
+        };
+
+        info!("Untracking user {}", login);
+        self.tracked.untrack(&login)?;
+        self.config.users.retain(|user| user != &login);
+        self.collector.remove_source(&Source::User(login.clone()));
+        self.status = self.collector.status();
+
+        Ok(Some(login))
+    }
+
+    /// The configured users together with the ones tracked at runtime.
+    pub fn users(&self) -> &[String] {
+        &self.config.users
+    }
+
+    /// Stores the given events and publishes the ones that were not published
+    /// yet.
+    pub fn ingest(&mut self, events: Vec<Event>) -> Result<(), GabelnError> {
+        let added = self.store.merge(events.clone(), self.config.retention_days).len();
+        let pruned = self.config.retention_days.map_or(0, |days| self.store.prune(days));
+        info!("Stored {} new events, pruned {} old events", added, pruned);
+        if added > 0 || pruned > 0 {
+            self.store.save()?;
+        }
+
+        self.events = self.store.events();
+        self.render_feeds()?;
+
+        if self.seen.is_fresh() {
+            info!(
+                "No seen events log found, marking {} current events as seen without publishing them",
+                self.events.len(),
+            );
+            let keys = self.events
+                .iter()
+                .flat_map(|event| Some(event.id.clone()).into_iter().chain(event.fingerprint()))
+                .collect::<Vec<String>>();
+            return self.seen.insert_all(keys.iter().map(|key| key.as_str()));
+        }
+
+        for event in events.iter() {
+            let fingerprint = event.fingerprint();
+            let seen = self.seen.contains(&event.id)
+                || fingerprint.as_ref().map_or(false, |fingerprint| self.seen.contains(fingerprint));
+            if seen {
+                continue;
             }
+
+            info!("Publishing new event: {}", event.summary());
+            self.sender
+                .unbounded_send(event.clone())
+                .map_err(|_| GabelnError::FailedToPublishEvents)?;
+            let keys = Some(event.id.clone()).into_iter().chain(fingerprint).collect::<Vec<String>>();
+            self.seen.insert_all(keys.iter().map(|key| key.as_str()))?;
         }
 
         Ok(())
@@ -1,133 +1,968 @@
-use reqwest::{Client, header::AUTHORIZATION};
-use regex::Regex;
-use chrono::{DateTime, Utc};
+use reqwest::{Client, Response, StatusCode};
+use reqwest::header::{ACCEPT, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use rayon::prelude::*;
-use std::env;
+use serde_json::{self, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
 
 use error::GabelnError;
+use link;
 
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Actor {
     pub display_login: String,
     pub avatar_url: String,
 }
 
-#[derive(Deserialize, Clone)]
+/// Time after which the metadata of a repository is fetched again.
+const METADATA_MAX_AGE_HOURS: i64 = 24;
+
+/// Base URL of the public GitHub API.
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Repository {
     pub name: String,
+    /// Details of the repository at the time the event was collected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RepositoryMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RepositoryMetadata {
+    pub description: Option<String>,
+    /// Primary language as detected by GitHub
+    pub language: Option<String>,
+    pub stargazers_count: u64,
+    /// SPDX identifier or name of the license
+    pub license: Option<String>,
+    pub topics: Vec<String>,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize)]
+struct GitHubLicense {
+    name: String,
+    spdx_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitHubUser {
+    login: String,
+    #[serde(rename = "type")]
+    user_type: String,
+}
+
+/// A repository as returned by the GitHub API and sent in webhooks.
+#[derive(Deserialize)]
+pub struct GitHubRepository {
+    pub full_name: String,
+    description: Option<String>,
+    language: Option<String>,
+    stargazers_count: u64,
+    license: Option<GitHubLicense>,
+    #[serde(default)]
+    topics: Vec<String>,
+}
+
+impl GitHubRepository {
+    pub fn metadata(&self) -> RepositoryMetadata {
+        RepositoryMetadata {
+            description: self.description.clone(),
+            language: self.language.clone(),
+            stargazers_count: self.stargazers_count,
+            license: self.license
+                .as_ref()
+                .map(|license| match license.spdx_id {
+                    Some(ref spdx_id) if spdx_id != "NOASSERTION" => spdx_id.clone(),
+                    _ => license.name.clone(),
+                }),
+            topics: self.topics.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Forkee {
+    /// Id of the fork, missing for forks stored by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
     pub full_name: String,
     pub html_url: String,
 }
 
-#[derive(Deserialize, Clone)]
-pub struct Payload {
-    pub forkee: Option<Forkee>,
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub html_url: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EventType {
+    Fork,
+    Watch,
+    Create,
+    Release,
+    Public,
+}
+
+impl EventType {
+    pub fn from_github_name(name: &str) -> Option<Self> {
+        match name {
+            "ForkEvent" => Some(EventType::Fork),
+            "WatchEvent" => Some(EventType::Watch),
+            "CreateEvent" => Some(EventType::Create),
+            "ReleaseEvent" => Some(EventType::Release),
+            "PublicEvent" => Some(EventType::Public),
+            _ => None,
+        }
+    }
+}
+
+/// Where on GitHub an event was collected from.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Source {
+    /// Public events of a user
+    User(String),
+    /// Public events of an organization and its repositories
+    Org(String),
+    /// Events of a single repository given as `owner/name`
+    Repo(String),
 }
 
-#[derive(Deserialize, Clone)]
+impl Source {
+    fn events_path(&self) -> String {
+        match *self {
+            Source::User(ref login) => format!("/users/{}/events/public", login),
+            Source::Org(ref org) => format!("/orgs/{}/events", org),
+            Source::Repo(ref repo) => format!("/repos/{}/events", repo),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Source::User(ref login) => write!(f, "user {}", login),
+            Source::Org(ref org) => write!(f, "organization {}", org),
+            Source::Repo(ref repo) => write!(f, "repository {}", repo),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum Payload {
+    Fork { forkee: Forkee },
+    Watch,
+    Create { description: Option<String> },
+    Release { release: Release },
+    Public,
+}
+
+impl Payload {
+    pub fn event_type(&self) -> EventType {
+        match *self {
+            Payload::Fork { .. } => EventType::Fork,
+            Payload::Watch => EventType::Watch,
+            Payload::Create { .. } => EventType::Create,
+            Payload::Release { .. } => EventType::Release,
+            Payload::Public => EventType::Public,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Event {
     pub id: String,
-    #[serde(rename = "type")]
-    pub event_type: String,
     pub actor: Actor,
     pub repo: Repository,
     pub payload: Payload,
     pub created_at: DateTime<Utc>,
+    pub source: Source,
+}
+
+impl Event {
+    /// A plain text sentence describing what happened.
+    pub fn summary(&self) -> String {
+        match self.payload {
+            Payload::Fork { ref forkee } => format!(
+                "{} forked {} at {}", self.actor.display_login, self.repo.name, forkee.full_name,
+            ),
+            Payload::Watch => format!(
+                "{} starred {}", self.actor.display_login, self.repo.name,
+            ),
+            Payload::Create { .. } => format!(
+                "{} created the repository {}", self.actor.display_login, self.repo.name,
+            ),
+            Payload::Release { ref release } => format!(
+                "{} released {} of {}", self.actor.display_login, release.tag_name, self.repo.name,
+            ),
+            Payload::Public => format!(
+                "{} made {} public", self.actor.display_login, self.repo.name,
+            ),
+        }
+    }
+
+    /// Identifies a fork independently of how it was received, `None` for
+    /// all other events.
+    ///
+    /// Webhook deliveries and the events API use different ids for the same
+    /// fork, but both carry the id of the forked repository. A fork that is
+    /// deleted and created again gets a new id.
+    pub fn fingerprint(&self) -> Option<String> {
+        match self.payload {
+            Payload::Fork { ref forkee } => forkee.id.map(|id| format!("fork:{}", id)),
+            _ => None,
+        }
+    }
+
+    /// Whether the repository, the fork or the login of the user contains the
+    /// search term, ignoring case.
+    pub fn matches_term(&self, term: &str) -> bool {
+        let term = term.to_lowercase();
+        let forkee = match self.payload {
+            Payload::Fork { ref forkee } => forkee.full_name.to_lowercase(),
+            _ => String::new(),
+        };
+
+        self.repo.name.to_lowercase().contains(&term)
+            || self.actor.display_login.to_lowercase().contains(&term)
+            || forkee.contains(&term)
+    }
+
+    /// The page on github.com that is most relevant for this event.
+    pub fn html_url(&self) -> String {
+        match self.payload {
+            Payload::Fork { ref forkee } => forkee.html_url.clone(),
+            Payload::Release { ref release } => release.html_url.clone(),
+            _ => format!("https://github.com/{}", self.repo.name),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawEvent {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    actor: Actor,
+    repo: Repository,
+    payload: Value,
+    created_at: DateTime<Utc>,
 }
 
+#[derive(Deserialize)]
+struct ForkPayload {
+    forkee: Forkee,
+}
+
+#[derive(Deserialize)]
+struct CreatePayload {
+    ref_type: String,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReleasePayload {
+    action: String,
+    release: Release,
+}
+
+impl RawEvent {
+    fn into_event(self, source: &Source, event_types: &[EventType]) -> Option<Event> {
+        let event_type = EventType::from_github_name(&self.event_type)?;
+
+        if !event_types.contains(&event_type) {
+            return None;
+        }
+
+        let payload = match event_type {
+            EventType::Fork => {
+                let payload = serde_json::from_value::<ForkPayload>(self.payload).ok()?;
+                Payload::Fork { forkee: payload.forkee }
+            },
+            EventType::Watch => Payload::Watch,
+            EventType::Create => {
+                let payload = serde_json::from_value::<CreatePayload>(self.payload).ok()?;
+                if payload.ref_type != "repository" {
+                    return None;
+                }
+                Payload::Create { description: payload.description }
+            },
+            EventType::Release => {
+                let payload = serde_json::from_value::<ReleasePayload>(self.payload).ok()?;
+                if payload.action != "published" {
+                    return None;
+                }
+                Payload::Release { release: payload.release }
+            },
+            EventType::Public => Payload::Public,
+        };
+
+        Some(Event {
+            id: self.id,
+            actor: self.actor,
+            repo: self.repo,
+            payload: payload,
+            created_at: self.created_at,
+            source: source.clone(),
+        })
+    }
+}
+
+/// Validators and events of the last successful poll of a source.
 #[derive(Clone)]
-pub struct EventCollector<'a> {
+struct CachedSource {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    next_poll: Option<DateTime<Utc>>,
+    /// Creation time of the newest event of any type seen so far.
+    newest: Option<DateTime<Utc>>,
+    events: Vec<Event>,
+}
+
+/// Metadata of a repository, `None` if it could not be found.
+#[derive(Clone)]
+struct CachedMetadata {
+    fetched_at: DateTime<Utc>,
+    metadata: Option<RepositoryMetadata>,
+}
+
+/// Health of a source after the last poll.
+#[derive(Clone)]
+pub struct SourceStatus {
+    pub source: Source,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Whether the events shown for this source are from an earlier poll.
+    pub stale: bool,
+}
+
+/// The GitHub API rate limit as reported by the last response.
+#[derive(Clone, Copy)]
+struct RateLimit {
+    remaining: u32,
+    reset: DateTime<Utc>,
+}
+
+pub struct EventCollector {
     client: Client,
-    re: Regex,
-    users: Vec<&'a str>,
+    api_url: String,
+    sources: Mutex<Vec<Source>>,
+    max_pages: u32,
+    event_types: Vec<EventType>,
     oauth_token: String,
+    cache: Mutex<HashMap<Source, CachedSource>>,
+    metadata: Mutex<HashMap<String, CachedMetadata>>,
+    rate_limit: Mutex<Option<RateLimit>>,
+    status: Mutex<HashMap<Source, SourceStatus>>,
 }
 
-impl<'a> Default for EventCollector<'a> {
-    fn default() -> Self {
+impl EventCollector {
+    /// `api_url` is the base URL of the GitHub API, usually `GITHUB_API_URL`.
+    pub fn new(api_url: &str, oauth_token: Option<String>) -> Self {
         Self {
             client: Client::new(),
-            re: Regex::new(",?.*page=\\d+.*; rel=\"next\",?.*").unwrap(),
-            users: Vec::new(),
-            oauth_token: env::var("GITHUB_OAUTH_TOKEN")
-                .unwrap_or_else(|_| {
-                    warn!("The environment variable GITHUB_OAUTH_TOKEN was not set! You will run into GitHub rate limiting!");
-                    String::new()
-                }),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            sources: Mutex::new(Vec::new()),
+            max_pages: 3,
+            event_types: vec![EventType::Fork],
+            oauth_token: oauth_token.unwrap_or_default(),
+            cache: Mutex::new(HashMap::new()),
+            metadata: Mutex::new(HashMap::new()),
+            rate_limit: Mutex::new(None),
+            status: Mutex::new(HashMap::new()),
         }
     }
-}
 
-impl<'a> EventCollector<'a> {
-    pub fn add_users(mut self, mut users: Vec<&'a str>) -> Self {
-        self.users.append(&mut users);
+    pub fn add_sources(mut self, mut sources: Vec<Source>) -> Self {
+        self.sources.get_mut().unwrap().append(&mut sources);
 
         self
     }
 
-    pub fn collect(self) -> Result<Vec<Event>, GabelnError> {
-        debug!("Collecting events for users {}", self.users.join(", "));
+    pub fn add_source(&self, source: Source) {
+        let mut sources = self.sources.lock().unwrap();
 
-        let mut events = self.users
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+
+    pub fn remove_source(&self, source: &Source) {
+        self.sources.lock().unwrap().retain(|known| known != source);
+        self.cache.lock().unwrap().remove(source);
+        self.status.lock().unwrap().remove(source);
+    }
+
+    pub fn max_pages(mut self, max_pages: u32) -> Self {
+        self.max_pages = max_pages;
+
+        self
+    }
+
+    pub fn event_types(mut self, event_types: Vec<EventType>) -> Self {
+        self.event_types = event_types;
+
+        self
+    }
+
+    /// Seeds the cache with events stored by an earlier run, so the first
+    /// poll of each source stops at the newest of them.
+    pub fn known_events(mut self, events: &[Event]) -> Self {
+        {
+            let cache = self.cache.get_mut().unwrap();
+
+            for event in events {
+                let cached = cache.entry(event.source.clone()).or_insert_with(|| CachedSource {
+                    etag: None,
+                    last_modified: None,
+                    next_poll: None,
+                    newest: None,
+                    events: Vec::new(),
+                });
+
+                cached.newest = cached.newest.into_iter().chain(Some(event.created_at)).max();
+                cached.events.push(event.clone());
+            }
+        }
+
+        self
+    }
+
+    pub fn collect(&self) -> Vec<Event> {
+        let sources = self.sources.lock().unwrap().clone();
+        debug!(
+            "Collecting events for {}",
+            sources.iter().map(|source| source.to_string()).collect::<Vec<String>>().join(", "),
+        );
+
+        let results = sources
             .par_iter()
-            .map(|username| self.get_events_of_user(username))
-            .collect::<Result<Vec<Vec<Event>>, GabelnError>>()?
+            .map(|source| (source, self.get_events_of_source(source)))
+            .collect::<Vec<(&Source, Result<Vec<Event>, GabelnError>)>>();
+
+        let now = Utc::now();
+        let mut ids = HashSet::new();
+        let mut events = results
             .into_iter()
+            .map(|(source, result)| {
+                let mut status = self.status.lock().unwrap();
+                let status = status
+                    .entry(source.clone())
+                    .or_insert_with(|| SourceStatus {
+                        source: source.clone(),
+                        last_success: None,
+                        last_error: None,
+                        stale: false,
+                    });
+
+                match result {
+                    Ok(events) => {
+                        status.last_success = Some(now);
+                        status.last_error = None;
+                        status.stale = false;
+
+                        events
+                    },
+                    Err(e) => {
+                        warn!("Keeping previous events of {}: {}", source, e);
+                        status.last_error = Some(e.to_string());
+                        status.stale = true;
+
+                        self.cache
+                            .lock()
+                            .unwrap()
+                            .get(source)
+                            .map(|cached| cached.events.clone())
+                            .unwrap_or_default()
+                    },
+                }
+            })
             .flatten()
+            .filter(|event| ids.insert(event.id.clone()))
             .collect::<Vec<Event>>();
 
+        if let Some(rate_limit) = *self.rate_limit.lock().unwrap() {
+            info!(
+                "GitHub rate limit: {} requests remaining until {}",
+                rate_limit.remaining,
+                rate_limit.reset.to_rfc3339(),
+            );
+        }
+
+        let stale = self.status()
+            .into_iter()
+            .filter(|status| status.stale)
+            .map(|status| status.source.to_string())
+            .collect::<Vec<String>>();
+        if !stale.is_empty() {
+            warn!("Stale sources: {}", stale.join(", "));
+        }
+
         debug!("Sorting events by timestamp");
         events.sort_unstable_by_key(|ev| ev.created_at);
 
-        Ok(events)
+        events
     }
 
-    fn get_events_of_user(&self, user: &str) -> Result<Vec<Event>, GabelnError> {
-        let mut page: u32 = 1;
+    /// Attaches the metadata of their repository to the given events.
+    pub fn enrich(&self, events: Vec<&mut Event>) {
+        let now = Utc::now();
+        let outdated = {
+            let metadata = self.metadata.lock().unwrap();
+
+            events
+                .iter()
+                .map(|event| event.repo.name.clone())
+                .filter(|name| {
+                    metadata
+                        .get(name)
+                        .map_or(true, |cached| now - cached.fetched_at > Duration::hours(METADATA_MAX_AGE_HOURS))
+                })
+                .collect::<HashSet<String>>()
+        };
+
+        debug!("Fetching metadata of {} repositories", outdated.len());
+        let fetched = outdated
+            .par_iter()
+            .filter_map(|name| match self.get_repository_metadata(name) {
+                Ok(metadata) => Some((name.clone(), metadata)),
+                Err(e) => {
+                    warn!("Keeping previous metadata of {}: {}", name, e);
+                    None
+                },
+            })
+            .collect::<Vec<(String, Option<RepositoryMetadata>)>>();
+
+        let mut metadata = self.metadata.lock().unwrap();
+        for (name, fetched) in fetched {
+            metadata.insert(name, CachedMetadata {
+                fetched_at: now,
+                metadata: fetched,
+            });
+        }
+
+        for event in events {
+            if let Some(cached) = metadata.get(&event.repo.name) {
+                event.repo.metadata = cached.metadata.clone();
+            }
+        }
+    }
+
+    /// Looks up a GitHub user and returns the login with its proper case, or
+    /// `None` if there is no user (but maybe an organization) of that name.
+    pub fn lookup_user(&self, login: &str) -> Result<Option<String>, GabelnError> {
+        if let Some(reset) = self.rate_limited_until() {
+            return Err(GabelnError::RateLimited(reset.to_rfc3339()));
+        }
+
+        let url = format!("{}/users/{}", self.api_url, login);
+        debug!("Looking up user: {}", url);
+        let mut response = self.client
+            .get(&url)
+            .header(AUTHORIZATION, format!("token {}", self.oauth_token))
+            .send()
+            .map_err(|e| {
+                error!("Failed to look up user: {}", e);
+                GabelnError::FailedToFetchUser(login.to_string())
+            })?;
+
+        self.update_rate_limit(&response);
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            error!("Failed to look up user {}: {}", login, response.status());
+            return Err(GabelnError::FailedToFetchUser(login.to_string()));
+        }
+
+        let user = response
+            .json::<GitHubUser>()
+            .map_err(|_| GabelnError::FailedToFetchUser(login.to_string()))?;
+
+        Ok(if user.user_type == "User" { Some(user.login) } else { None })
+    }
+
+    fn get_repository_metadata(&self, name: &str) -> Result<Option<RepositoryMetadata>, GabelnError> {
+        if let Some(reset) = self.rate_limited_until() {
+            return Err(GabelnError::RateLimited(reset.to_rfc3339()));
+        }
+
+        let url = format!("{}/repos/{}", self.api_url, name);
+        debug!("Fetching repository metadata: {}", url);
+        let mut response = self.client
+            .get(&url)
+            .header(AUTHORIZATION, format!("token {}", self.oauth_token))
+            // topics are only included in the mercy preview
+            .header(ACCEPT, "application/vnd.github.mercy-preview+json")
+            .send()
+            .map_err(|e| {
+                error!("Failed to fetch repository metadata: {}", e);
+                GabelnError::FailedToFetchRepository(name.to_string())
+            })?;
+
+        self.update_rate_limit(&response);
+
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!("Repository {} does not exist anymore", name);
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            error!("Failed to fetch repository metadata of {}: {}", name, response.status());
+            return Err(GabelnError::FailedToFetchRepository(name.to_string()));
+        }
+
+        response
+            .json::<GitHubRepository>()
+            .map(|repository| Some(repository.metadata()))
+            .map_err(|_| GabelnError::FailedToFetchRepository(name.to_string()))
+    }
+
+    /// The health of all sources in the order they were added.
+    pub fn status(&self) -> Vec<SourceStatus> {
+        let status = self.status.lock().unwrap();
+
+        self.sources
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|source| {
+                status
+                    .get(source)
+                    .cloned()
+                    .unwrap_or_else(|| SourceStatus {
+                        source: source.clone(),
+                        last_success: None,
+                        last_error: None,
+                        stale: false,
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the time of the next rate limit reset if no requests are left.
+    fn rate_limited_until(&self) -> Option<DateTime<Utc>> {
+        self.rate_limit
+            .lock()
+            .unwrap()
+            .and_then(|rate_limit| {
+                if rate_limit.remaining == 0 && rate_limit.reset > Utc::now() {
+                    Some(rate_limit.reset)
+                } else {
+                    None
+                }
+            })
+    }
+
+    fn update_rate_limit(&self, response: &Response) {
+        let remaining = header(response, "X-RateLimit-Remaining")
+            .and_then(|remaining| remaining.parse::<u32>().ok());
+        let reset = header(response, "X-RateLimit-Reset")
+            .and_then(|reset| reset.parse::<i64>().ok())
+            .and_then(|reset| Utc.timestamp_opt(reset, 0).single());
+        let retry_after = header(response, "Retry-After")
+            .and_then(|retry_after| seconds_after(Utc::now(), &retry_after));
+        let limited = response.status() == StatusCode::FORBIDDEN
+            || response.status() == StatusCode::TOO_MANY_REQUESTS;
+
+        let mut rate_limit = self.rate_limit.lock().unwrap();
+
+        if let (Some(remaining), Some(reset)) = (remaining, reset) {
+            debug!("GitHub rate limit: {} requests remaining until {}", remaining, reset.to_rfc3339());
+            *rate_limit = Some(RateLimit {
+                remaining: remaining,
+                reset: reset,
+            });
+        }
+
+        if limited {
+            if let Some(retry_after) = retry_after {
+                *rate_limit = Some(RateLimit {
+                    remaining: 0,
+                    reset: retry_after,
+                });
+            }
+        }
+    }
+
+    fn get_events_of_source(&self, source: &Source) -> Result<Vec<Event>, GabelnError> {
+        let now = Utc::now();
+        let mut cached = self.cache.lock().unwrap().get(source).cloned();
+
+        if let Some(ref cached) = cached {
+            if cached.next_poll.map_or(false, |next_poll| next_poll > now) {
+                debug!("Poll interval of {} has not elapsed yet, reusing cached events", source);
+                return Ok(cached.events.clone());
+            }
+        }
+
+        let known_until = cached.as_ref().and_then(|cached| cached.newest);
+        let mut next_url = Some(format!("{}{}?per_page=100", self.api_url, source.events_path()));
+        let mut page: u32 = 0;
         let mut events = Vec::new();
+        let mut newest = known_until;
+        let mut etag = None;
+        let mut last_modified = None;
+        let mut next_poll = None;
 
-        debug!("Collecting events for user {}", user);
+        debug!("Collecting events for {}", source);
+
+        while let Some(url) = next_url.take() {
+            if page >= self.max_pages {
+                debug!("Reached page limit of {} for {}", self.max_pages, source);
+                break;
+            }
+            page += 1;
 
-        loop {
-            let url = format!("https://api.github.com/users/{}/events/public?page={}&per_page=300", user, page);
-            debug!("Crawling user API: {}", url);
-            let mut response = self.client
+            if let Some(reset) = self.rate_limited_until() {
+                return Err(GabelnError::RateLimited(reset.to_rfc3339()));
+            }
+
+            debug!("Crawling events API: {}", url);
+            let mut request = self.client
                 .get(&url)
-                .header(AUTHORIZATION, format!("token {}", self.oauth_token))
+                .header(AUTHORIZATION, format!("token {}", self.oauth_token));
+
+            if page == 1 {
+                if let Some(ref cached) = cached {
+                    if let Some(ref etag) = cached.etag {
+                        request = request.header(IF_NONE_MATCH, etag.as_str());
+                    }
+                    if let Some(ref last_modified) = cached.last_modified {
+                        request = request.header(IF_MODIFIED_SINCE, last_modified.as_str());
+                    }
+                }
+            }
+
+            let mut response = request
                 .send()
                 .map_err(|e| {
-                    error!("Failed to fetch user events: {}", e);
-                    GabelnError::FailedToFetchUserEvents(user.into())
+                    error!("Failed to fetch events: {}", e);
+                    GabelnError::FailedToFetchEvents(source.to_string())
                 })?;
 
-            {
-                let links = response
-                    .headers()
-                    .get("Link")
-                    .and_then(|l| l.to_str().ok())
-                    .unwrap_or("");
+            self.update_rate_limit(&response);
 
-                if self.re.is_match(links) {
-                    page += 1;
-                } else {
-                    break;
+            if let Some(reset) = self.rate_limited_until() {
+                if !response.status().is_success() && response.status() != StatusCode::NOT_MODIFIED {
+                    return Err(GabelnError::RateLimited(reset.to_rfc3339()));
+                }
+            }
+
+            if page == 1 {
+                next_poll = header(&response, "X-Poll-Interval")
+                    .and_then(|interval| seconds_after(now, &interval));
+
+                if response.status() == StatusCode::NOT_MODIFIED {
+                    if let Some(mut cached) = cached.take() {
+                        debug!("Events of {} were not modified, reusing cached events", source);
+                        cached.next_poll = next_poll;
+                        let events = cached.events.clone();
+                        self.cache.lock().unwrap().insert(source.clone(), cached);
+
+                        return Ok(events);
+                    }
                 }
+
+                etag = header(&response, ETAG.as_str());
+                last_modified = header(&response, LAST_MODIFIED.as_str());
             }
 
+            if !response.status().is_success() {
+                error!("Failed to fetch events of {}: {}", source, response.status());
+                return Err(GabelnError::FailedToFetchEvents(source.to_string()));
+            }
+
+            next_url = header(&response, "Link").and_then(|links| link::next_link(&links));
+
+            let raw_events = response
+                .json::<Vec<RawEvent>>()
+                .map_err(|_| GabelnError::FailedToParseEvents)?;
+            let reached_known = raw_events
+                .iter()
+                .any(|event| known_until.map_or(false, |known_until| event.created_at <= known_until));
+
+            newest = raw_events
+                .iter()
+                .map(|event| event.created_at)
+                .chain(newest)
+                .max();
             events.append(
-                &mut response
-                    .json::<Vec<Event>>()
-                    .map_err(|_| GabelnError::FailedToParseUserEvents)?
+                &mut raw_events
                     .into_iter()
-                    .filter(|event| event.event_type == "ForkEvent")
+                    .filter_map(|event| event.into_event(source, &self.event_types))
                     .collect::<Vec<Event>>()
             );
+
+            if reached_known {
+                debug!("Reached already known events of {}", source);
+                break;
+            }
         }
 
+        if let Some(cached) = cached {
+            // events older than the ones GitHub still returns are kept from earlier polls
+            let ids = events.iter().map(|event| event.id.clone()).collect::<HashSet<String>>();
+            let horizon = now - Duration::days(90);
+            events.extend(
+                cached.events
+                    .into_iter()
+                    .filter(|event| !ids.contains(&event.id) && event.created_at > horizon)
+            );
+        }
+
+        self.cache.lock().unwrap().insert(source.clone(), CachedSource {
+            etag: etag,
+            last_modified: last_modified,
+            next_poll: next_poll,
+            newest: newest,
+            events: events.clone(),
+        });
+
         Ok(events)
     }
 }
+
+fn header(response: &Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+/// The time a header value in seconds points to, `None` if it is negative,
+/// malformed or out of range.
+fn seconds_after(now: DateTime<Utc>, seconds: &str) -> Option<DateTime<Utc>> {
+    let seconds = seconds.trim().parse::<u32>().ok()?;
+
+    now.checked_add_signed(Duration::seconds(i64::from(seconds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::MAX_DATE;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+
+    const EVENTS: &str = r#"[{
+        "id": "1",
+        "type": "ForkEvent",
+        "actor": {"display_login": "octocat", "avatar_url": "https://avatars.githubusercontent.com/u/583231"},
+        "repo": {"name": "rust-lang/rust"},
+        "payload": {"forkee": {"full_name": "octocat/rust", "html_url": "https://github.com/octocat/rust"}},
+        "created_at": "2019-10-01T12:00:00Z"
+    }]"#;
+
+    fn response(status: &str, headers: &[&str], body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers.iter().map(|header| format!("{}\r\n", header)).collect::<String>(),
+            body.len(),
+            body,
+        )
+    }
+
+    /// Answers one request per connection with the given responses in order
+    /// and records the head of every request.
+    fn serve(responses: Vec<String>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut head = String::new();
+
+                {
+                    let mut reader = BufReader::new(&stream);
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                        head.push_str(&line.to_lowercase());
+                        line.clear();
+                    }
+                }
+
+                recorded.lock().unwrap().push(head);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn ids(events: &[Event]) -> Vec<&str> {
+        events.iter().map(|event| event.id.as_str()).collect()
+    }
+
+    #[test]
+    fn reuses_cached_events_if_not_modified() {
+        let (url, requests) = serve(vec![
+            response("200 OK", &["ETag: \"abc\"", "X-Poll-Interval: 0"], EVENTS),
+            response("304 Not Modified", &["ETag: \"abc\"", "X-Poll-Interval: 60"], ""),
+        ]);
+        let source = Source::User("octocat".to_string());
+        let collector = EventCollector::new(&url, None).add_sources(vec![source.clone()]);
+
+        let events = collector.get_events_of_source(&source).unwrap();
+        assert_eq!(ids(&events), vec!["1"]);
+
+        let events = collector.get_events_of_source(&source).unwrap();
+        assert_eq!(ids(&events), vec!["1"]);
+
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            assert!(requests[0].starts_with("get /users/octocat/events/public?per_page=100 "));
+            assert!(!requests[0].contains("if-none-match"));
+            assert!(requests[1].contains("if-none-match: \"abc\""));
+        }
+
+        // the poll interval sent with the 304 defers the next request
+        let events = collector.get_events_of_source(&source).unwrap();
+        assert_eq!(ids(&events), vec!["1"]);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn stops_at_stored_events() {
+        let link = "Link: <https://api.github.com/user/583231/events/public?per_page=100&page=2>; rel=\"next\"";
+        let (url, requests) = serve(vec![response("200 OK", &[link], EVENTS)]);
+        let source = Source::User("octocat".to_string());
+        let stored = serde_json::from_str::<Vec<RawEvent>>(EVENTS)
+            .unwrap()
+            .into_iter()
+            .filter_map(|event| event.into_event(&source, &[EventType::Fork]))
+            .collect::<Vec<Event>>();
+        let collector = EventCollector::new(&url, None)
+            .add_sources(vec![source.clone()])
+            .known_events(&stored);
+
+        // the second page is never requested, as the first one reaches the stored fork
+        let events = collector.get_events_of_source(&source).unwrap();
+        assert_eq!(ids(&events), vec!["1"]);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn ignores_invalid_seconds() {
+        let now = Utc.ymd(2019, 10, 1).and_hms(12, 0, 0);
+
+        assert_eq!(seconds_after(now, "60"), Some(Utc.ymd(2019, 10, 1).and_hms(12, 1, 0)));
+        assert_eq!(seconds_after(now, "-1"), None);
+        assert_eq!(seconds_after(now, "soon"), None);
+        assert_eq!(seconds_after(now, "99999999999999999999"), None);
+        assert_eq!(seconds_after(MAX_DATE.and_hms(0, 0, 0), "4294967295"), None);
+    }
+}
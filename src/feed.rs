@@ -1,18 +1,107 @@
 use chrono::Utc;
+use maud::html;
 use atom_syndication::{Feed, FeedBuilder, PersonBuilder, LinkBuilder, EntryBuilder, ContentBuilder};
+use rss::{Channel, ChannelBuilder, GuidBuilder, ImageBuilder, ItemBuilder};
+use rss::extension::dublin_core::DublinCoreExtensionBuilder;
+use std::collections::HashMap;
 
 use error::GabelnError;
-use events::Event;
+use events::{Event, Payload};
+use config::SiteConfig;
 
-pub fn create_feed(events: &Vec<Event>) -> Result<Feed, GabelnError> {
+/// Number of newest events that are included in the feed.
+pub const FEED_ENTRIES: usize = 100;
+
+/// Title and summary of the feed entry of an event.
+fn entry_text(event: &Event) -> (String, String) {
+    match event.payload {
+        Payload::Fork { ref forkee } => (
+            format!("{} forked {}", event.actor.display_login, event.repo.name),
+            format!(
+                "{} was forked by {} at {}.",
+                event.repo.name,
+                event.actor.display_login,
+                forkee.full_name,
+            ),
+        ),
+        Payload::Watch => (
+            format!("{} starred {}", event.actor.display_login, event.repo.name),
+            format!("{} was starred by {}.", event.repo.name, event.actor.display_login),
+        ),
+        Payload::Create { ref description } => (
+            format!("{} created {}", event.actor.display_login, event.repo.name),
+            match *description {
+                Some(ref description) => format!(
+                    "{} created the repository {}: {}",
+                    event.actor.display_login,
+                    event.repo.name,
+                    description,
+                ),
+                None => format!(
+                    "{} created the repository {}.",
+                    event.actor.display_login,
+                    event.repo.name,
+                ),
+            },
+        ),
+        Payload::Release { ref release } => (
+            format!(
+                "{} released {} of {}",
+                event.actor.display_login,
+                release.tag_name,
+                event.repo.name,
+            ),
+            format!(
+                "{} of {} was released by {}.",
+                release.name.clone().unwrap_or(release.tag_name.clone()),
+                event.repo.name,
+                event.actor.display_login,
+            ),
+        ),
+        Payload::Public => (
+            format!("{} made {} public", event.actor.display_login, event.repo.name),
+            format!("{} was made public by {}.", event.repo.name, event.actor.display_login),
+        ),
+    }
+}
+
+/// HTML content of the feed entry of an event.
+fn entry_content(event: &Event) -> String {
+    html! {
+        a href=(event.html_url()) {
+            img src=(event.actor.avatar_url) alt=(event.actor.display_login);
+        }
+        @if let Some(ref metadata) = event.repo.metadata {
+            @if let Some(ref description) = metadata.description {
+                p { (description) }
+            }
+            p {
+                @if let Some(ref language) = metadata.language {
+                    (language) " · "
+                }
+                "★ " (metadata.stargazers_count)
+                @if let Some(ref license) = metadata.license {
+                    " · " (license)
+                }
+            }
+            @if !metadata.topics.is_empty() {
+                p { "Topics: " (metadata.topics.join(", ")) }
+            }
+        }
+    }.into_string()
+}
+
+pub fn create_feed(events: &Vec<Event>, site: &SiteConfig) -> Result<Feed, GabelnError> {
     info!("Generating new atom feed");
     let mut entries = Vec::new();
 
-    for ref event in events.iter().rev() {
+    for ref event in events.iter().rev().take(FEED_ENTRIES) {
+        let (title, summary) = entry_text(event);
+
         entries.push(
             EntryBuilder::default()
-                .title(format!("{} forked {}", event.actor.display_login, event.repo.name))
-                .id(event.id.clone())
+                .title(title)
+                .id(site.tag_uri(event.created_at.naive_utc().date(), &format!("event:{}", event.id)))
                 .updated(event.created_at.to_rfc3339())
                 .authors(vec![
                     PersonBuilder::default()
@@ -23,28 +112,19 @@ pub fn create_feed(events: &Vec<Event>) -> Result<Feed, GabelnError> {
                 ])
                 .links(vec![
                     LinkBuilder::default()
-                        .href(event.payload.forkee.clone().unwrap().html_url)
-                        .rel("related")
+                        .href(event.html_url())
+                        .rel("alternate")
                         .mime_type(Some("text/html".into()))
-                        .title(event.payload.forkee.clone().unwrap().full_name)
+                        .title(event.summary())
                         .build()
                         .map_err(|_| GabelnError::FailedToCreateFeed)?
                 ])
                 .published(event.created_at.to_rfc3339())
-                .summary(format!(
-                    "{} was forked by {} at {}.",
-                    event.repo.name,
-                    event.actor.display_login,
-                    event.payload.forkee.clone().unwrap().full_name,
-                ))
+                .summary(summary)
                 .content(
                     ContentBuilder::default()
-                        .value(format!(
-                            "<a href=\"{}\"><img src=\"{}\"/></a>",
-                            event.payload.forkee.clone().unwrap().html_url,
-                            event.actor.avatar_url,
-                        ))
-                        .content_type(Some("text/html".into()))
+                        .value(entry_content(event))
+                        .content_type(Some("html".into()))
                         .build()
                         .map_err(|_| GabelnError::FailedToCreateFeed)?
                 )
@@ -55,51 +135,193 @@ pub fn create_feed(events: &Vec<Event>) -> Result<Feed, GabelnError> {
 
     Ok(
         FeedBuilder::default()
-            .title("gabeln.jetzt")
-            .id("gabeln.jetzt")
+            .title(site.title.clone())
+            .id(site.url("/atom.xml"))
             .updated(
                 events
                     .last()
                     .map_or(Utc::now(), |ev| ev.created_at)
                     .to_rfc3339()
             )
-            .authors(vec![
-                PersonBuilder::default()
-                    .name("Fin Christensen")
-                    .email(Some("fchristensen@embedded.enterprises".into()))
-                    .uri(Some("https://blog.like-a-fin.lol".into()))
-                    .build()
-                    .map_err(|_| GabelnError::FailedToCreateFeed)?,
-                PersonBuilder::default()
-                    .name("Johannes Wuensche")
-                    .email(Some("johannes.wuensche@st.ovgu.de".into()))
-                    .uri(Some("https://github.com/jwuensche".into()))
-                    .build()
-                    .map_err(|_| GabelnError::FailedToCreateFeed)?,
-            ])
-            .icon(Some("/assets/icon.jpg".into()))
+            .authors(
+                site.authors
+                    .iter()
+                    .map(|author| {
+                        PersonBuilder::default()
+                            .name(author.name.clone())
+                            .email(author.email.clone())
+                            .uri(author.uri.clone())
+                            .build()
+                            .map_err(|_| GabelnError::FailedToCreateFeed)
+                    })
+                    .collect::<Result<Vec<_>, GabelnError>>()?
+            )
+            .icon(Some(site.url("/favicon.ico")))
             .links(vec![
                 LinkBuilder::default()
-                    .href("/atom.xml")
+                    .href(site.url("/atom.xml"))
                     .rel("self")
-                    .hreflang(Some("en".into()))
+                    .hreflang(Some(site.language.clone()))
                     .mime_type(Some("application/atom+xml".into()))
-                    .title(Some("gabeln.jetzt".into()))
+                    .title(Some(site.title.clone()))
                     .build()
                     .map_err(|_| GabelnError::FailedToCreateFeed)?,
                 LinkBuilder::default()
-                    .href("/")
+                    .href(site.url("/"))
                     .rel("alternate")
-                    .hreflang(Some("en".into()))
+                    .hreflang(Some(site.language.clone()))
                     .mime_type(Some("text/html".into()))
-                    .title(Some("gabeln.jetzt".into()))
+                    .title(Some(site.title.clone()))
                     .build()
                     .map_err(|_| GabelnError::FailedToCreateFeed)?,
             ])
-            .logo(Some("/assets/logo.jpg".into()))
+            .logo(Some(site.url("/logo.jpg")))
             .entries(entries)
-            .subtitle(Some("GitHub Fork Feed".into()))
+            .subtitle(Some(site.subtitle.clone()))
             .build()
             .map_err(|_| GabelnError::FailedToCreateFeed)?
     )
 }
+
+pub fn create_rss(events: &Vec<Event>, site: &SiteConfig) -> Result<Channel, GabelnError> {
+    info!("Generating new rss feed");
+    let mut items = Vec::new();
+
+    for ref event in events.iter().rev().take(FEED_ENTRIES) {
+        let (title, summary) = entry_text(event);
+
+        items.push(
+            ItemBuilder::default()
+                .title(Some(title))
+                .link(Some(event.html_url()))
+                .description(Some(summary))
+                .content(Some(entry_content(event)))
+                .guid(Some(
+                    GuidBuilder::default()
+                        .value(event.id.clone())
+                        .permalink(false)
+                        .build()
+                        .map_err(|_| GabelnError::FailedToCreateFeed)?
+                ))
+                .pub_date(Some(event.created_at.to_rfc2822()))
+                .dublin_core_ext(Some(
+                    DublinCoreExtensionBuilder::default()
+                        .creators(vec![event.actor.display_login.clone()])
+                        .build()
+                        .map_err(|_| GabelnError::FailedToCreateFeed)?
+                ))
+                .build()
+                .map_err(|_| GabelnError::FailedToCreateFeed)?
+        );
+    }
+
+    let mut namespaces = HashMap::new();
+    namespaces.insert("dc".to_string(), "http://purl.org/dc/elements/1.1/".to_string());
+    namespaces.insert("content".to_string(), "http://purl.org/rss/1.0/modules/content/".to_string());
+
+    ChannelBuilder::default()
+        .title(site.title.clone())
+        .link(site.url("/"))
+        .description(site.subtitle.clone())
+        .language(Some(site.language.clone()))
+        .managing_editor(
+            site.authors
+                .iter()
+                .find(|author| author.email.is_some())
+                .map(|author| format!("{} ({})", author.email.clone().unwrap(), author.name))
+        )
+        .last_build_date(Some(
+            events
+                .last()
+                .map_or(Utc::now(), |ev| ev.created_at)
+                .to_rfc2822()
+        ))
+        .image(Some(
+            ImageBuilder::default()
+                .url(site.url("/logo.jpg"))
+                .title(site.title.clone())
+                .link(site.url("/"))
+                .build()
+                .map_err(|_| GabelnError::FailedToCreateFeed)?
+        ))
+        .namespaces(namespaces)
+        .items(items)
+        .build()
+        .map_err(|_| GabelnError::FailedToCreateFeed)
+}
+
+#[derive(Serialize)]
+struct JsonFeedAuthor {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonFeedItem {
+    id: String,
+    url: String,
+    title: String,
+    summary: String,
+    content_html: String,
+    date_published: String,
+    authors: Vec<JsonFeedAuthor>,
+}
+
+/// A feed according to the JSON Feed 1.1 specification.
+#[derive(Serialize)]
+pub struct JsonFeed {
+    version: &'static str,
+    title: String,
+    home_page_url: String,
+    feed_url: String,
+    description: String,
+    icon: String,
+    favicon: String,
+    language: String,
+    authors: Vec<JsonFeedAuthor>,
+    items: Vec<JsonFeedItem>,
+}
+
+pub fn create_json_feed(events: &Vec<Event>, site: &SiteConfig) -> JsonFeed {
+    info!("Generating new json feed");
+
+    JsonFeed {
+        version: "https://jsonfeed.org/version/1.1",
+        title: site.title.clone(),
+        home_page_url: site.url("/"),
+        feed_url: site.url("/feed.json"),
+        description: site.subtitle.clone(),
+        icon: site.url("/logo.jpg"),
+        favicon: site.url("/favicon.ico"),
+        language: site.language.clone(),
+        authors: site.authors
+            .iter()
+            .map(|author| JsonFeedAuthor {
+                name: author.name.clone(),
+                url: author.uri.clone(),
+            })
+            .collect(),
+        items: events
+            .iter()
+            .rev()
+            .take(FEED_ENTRIES)
+            .map(|event| {
+                let (title, summary) = entry_text(event);
+
+                JsonFeedItem {
+                    id: event.id.clone(),
+                    url: event.html_url(),
+                    title: title,
+                    summary: summary,
+                    content_html: entry_content(event),
+                    date_published: event.created_at.to_rfc3339(),
+                    authors: vec![JsonFeedAuthor {
+                        name: event.actor.display_login.clone(),
+                        url: Some(format!("https://github.com/{}", event.actor.display_login)),
+                    }],
+                }
+            })
+            .collect(),
+    }
+}
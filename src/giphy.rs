@@ -1,13 +1,14 @@
 use rand::{thread_rng, seq::SliceRandom};
-use reqwest::Client;
-use std::env;
+use reqwest::{Client, Url};
 use error::GabelnError;
+use config::GiphyConfig;
 
 #[derive(Clone)]
 pub struct Giphy {
     client: Client,
     api_key: String,
-    gif_limit: String,
+    search_term: String,
+    gif_limit: u32,
 }
 
 #[derive(Deserialize, Clone)]
@@ -35,27 +36,43 @@ pub struct GiphyResponse {
 }
 
 impl Giphy {
-    pub fn new() -> Result<Self, GabelnError> {
+    pub fn new(config: &GiphyConfig) -> Result<Self, GabelnError> {
         Ok(Self {
             client: Client::new(),
-            api_key: env::var("GIPHY_API_KEY")
-                .map_err(|_| GabelnError::NoGiphyApiKey)?,
-            gif_limit: env::var("GIPHY_GIF_LIMIT")
-                .unwrap_or_else(|_| "30".to_string()),
+            api_key: config.api_key
+                .clone()
+                .ok_or(GabelnError::NoGiphyApiKey)?,
+            search_term: config.search_term.clone(),
+            gif_limit: config.gif_limit,
         })
     }
 
     pub fn get_gif(&self) -> Result<String, GabelnError> {
-        let url = format!(
-            "https://api.giphy.com/v1/gifs/search?api_key={}&q=fork+food&limit={}",
-            self.api_key,
-            self.gif_limit,
-        );
+        let gifs = self.get_gifs()?;
         let mut rng = thread_rng();
+
+        debug!("Selecting gif for telegram bot...");
+
+        match gifs.choose(&mut rng) {
+            Some(gif) => Ok(gif.url.clone()),
+            None => Err(GabelnError::FailedToParseGiphyResponse),
+        }
+    }
+
+    /// All gifs found for the search term.
+    pub fn get_gifs(&self) -> Result<Vec<GiphyOriginal>, GabelnError> {
+        let url = Url::parse_with_params(
+            "https://api.giphy.com/v1/gifs/search",
+            &[
+                ("api_key", self.api_key.clone()),
+                ("q", self.search_term.clone()),
+                ("limit", self.gif_limit.to_string()),
+            ],
+        ).map_err(|_| GabelnError::FailedToFetchGif)?;
         debug!("Fetching Giphy API: {}", url);
 
         let mut response = self.client
-            .get(&url)
+            .get(url.clone())
             .send()
             .map_err(|e| {
                 error!("Failed to fetch gif from giphy: {}", e);
@@ -66,11 +83,6 @@ impl Giphy {
             .json::<GiphyResponse>()
             .map_err(|_| GabelnError::FailedToParseGiphyResponse)?;
 
-        debug!("Selecting gif for telegram bot...");
-
-        match result.data.choose(&mut rng) {
-            Some(item) => Ok(item.images.original.url.clone()),
-            None => Err(GabelnError::FailedToParseGiphyResponse),
-        }
+        Ok(result.data.into_iter().map(|gif| gif.images.original).collect())
     }
 }
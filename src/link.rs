@@ -0,0 +1,191 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A single link of a `Link` header as described in RFC 8288.
+pub struct Link {
+    pub target: String,
+    pub rels: Vec<String>,
+}
+
+/// Parses the value of a `Link` header. Malformed links are skipped.
+pub fn parse_link_header(value: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut chars = value.chars().peekable();
+
+    loop {
+        skip_while(&mut chars, |c| c.is_whitespace() || c == ',');
+
+        match chars.next() {
+            Some('<') => {},
+            Some(_) => {
+                skip_while(&mut chars, |c| c != ',');
+                continue;
+            },
+            None => break,
+        }
+
+        let target = take_while(&mut chars, |c| c != '>');
+        if chars.next() != Some('>') {
+            break;
+        }
+
+        let mut rels = Vec::new();
+
+        loop {
+            skip_while(&mut chars, |c| c.is_whitespace());
+
+            if chars.peek() != Some(&';') {
+                break;
+            }
+            chars.next();
+            skip_while(&mut chars, |c| c.is_whitespace());
+
+            let name = take_while(&mut chars, |c| c != '=' && c != ';' && c != ',')
+                .trim()
+                .to_lowercase();
+            let value = if chars.peek() == Some(&'=') {
+                chars.next();
+                skip_while(&mut chars, |c| c.is_whitespace());
+                parse_param_value(&mut chars)
+            } else {
+                String::new()
+            };
+
+            // only the first occurrence of rel is taken into account
+            if name == "rel" && rels.is_empty() {
+                rels = value
+                    .split_whitespace()
+                    .map(|rel| rel.to_lowercase())
+                    .collect();
+            }
+        }
+
+        skip_while(&mut chars, |c| c != ',');
+        links.push(Link {
+            target: target,
+            rels: rels,
+        });
+    }
+
+    links
+}
+
+/// Returns the target of the first link with the relation `next`.
+pub fn next_link(value: &str) -> Option<String> {
+    parse_link_header(value)
+        .into_iter()
+        .find(|link| link.rels.iter().any(|rel| rel == "next"))
+        .map(|link| link.target)
+}
+
+fn parse_param_value(chars: &mut Peekable<Chars>) -> String {
+    if chars.peek() != Some(&'"') {
+        return take_while(chars, |c| c != ';' && c != ',').trim().to_string();
+    }
+
+    chars.next();
+    let mut value = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    value.push(escaped);
+                }
+            },
+            c => value.push(c),
+        }
+    }
+
+    value
+}
+
+fn take_while<F: Fn(char) -> bool>(chars: &mut Peekable<Chars>, predicate: F) -> String {
+    let mut result = String::new();
+
+    while let Some(&c) = chars.peek() {
+        if !predicate(c) {
+            break;
+        }
+        result.push(c);
+        chars.next();
+    }
+
+    result
+}
+
+fn skip_while<F: Fn(char) -> bool>(chars: &mut Peekable<Chars>, predicate: F) {
+    take_while(chars, predicate);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rels(value: &str) -> Vec<(String, Vec<String>)> {
+        parse_link_header(value)
+            .into_iter()
+            .map(|link| (link.target, link.rels))
+            .collect()
+    }
+
+    #[test]
+    fn parses_github_pagination() {
+        let value = "<https://api.github.com/user/583231/events/public?per_page=100&page=2>; rel=\"next\", \
+                     <https://api.github.com/user/583231/events/public?per_page=100&page=3>; rel=\"last\"";
+
+        assert_eq!(
+            next_link(value),
+            Some("https://api.github.com/user/583231/events/public?per_page=100&page=2".to_string())
+        );
+        assert_eq!(parse_link_header(value).len(), 2);
+    }
+
+    #[test]
+    fn no_next_link_on_the_last_page() {
+        let value = "<https://api.github.com/user/583231/events/public?per_page=100&page=2>; rel=\"prev\", \
+                     <https://api.github.com/user/583231/events/public?per_page=100&page=1>; rel=\"first\"";
+
+        assert_eq!(next_link(value), None);
+    }
+
+    #[test]
+    fn accepts_quoted_and_unquoted_rels() {
+        assert_eq!(next_link("<https://example.com/2>; rel=next"), Some("https://example.com/2".to_string()));
+        assert_eq!(next_link("<https://example.com/2>;REL=\"Next\""), Some("https://example.com/2".to_string()));
+        assert_eq!(
+            next_link("<https://example.com/1>; title=\"a; b, c\"; rel=prev, <https://example.com/2>; rel=next"),
+            Some("https://example.com/2".to_string())
+        );
+    }
+
+    #[test]
+    fn splits_multiple_rels() {
+        assert_eq!(
+            rels("<https://example.com/2>; rel=\"prev next\""),
+            vec![("https://example.com/2".to_string(), vec!["prev".to_string(), "next".to_string()])]
+        );
+        assert_eq!(next_link("<https://example.com/2>; rel=\"prev next\""), Some("https://example.com/2".to_string()));
+        // only the first rel parameter counts
+        assert_eq!(next_link("<https://example.com/2>; rel=prev; rel=next"), None);
+    }
+
+    #[test]
+    fn skips_malformed_links() {
+        assert_eq!(next_link(""), None);
+        assert_eq!(next_link("https://example.com/2; rel=next"), None);
+        assert_eq!(next_link("<https://example.com/2; rel=next"), None);
+        assert_eq!(
+            next_link("garbage; rel=next, <https://example.com/2>; rel=next"),
+            Some("https://example.com/2".to_string())
+        );
+        assert_eq!(
+            rels("<https://example.com/1>; rel, <https://example.com/2>"),
+            vec![
+                ("https://example.com/1".to_string(), Vec::new()),
+                ("https://example.com/2".to_string(), Vec::new()),
+            ]
+        );
+    }
+}
@@ -1,6 +1,5 @@
 #![feature(proc_macro_hygiene, decl_macro)]
 #![feature(impl_trait_in_bindings)]
-#![feature(vec_remove_item)]
 
 extern crate regex;
 extern crate reqwest;
@@ -9,6 +8,7 @@ extern crate chrono;
 extern crate chrono_humanize;
 extern crate rayon;
 extern crate atom_syndication;
+extern crate rss;
 extern crate maud;
 #[macro_use] extern crate rocket;
 extern crate rocket_contrib;
@@ -23,15 +23,32 @@ extern crate telegram_bot_fork;
 extern crate log;
 extern crate fern;
 extern crate rand;
+extern crate toml;
+extern crate hmac;
+extern crate sha2;
+extern crate hex;
+extern crate flate2;
 
 mod error;
+mod config;
 mod events;
 mod feed;
+mod cache;
 mod content;
+mod api;
+mod stats;
+mod query;
 mod event_manager;
 mod telegram;
 mod giphy;
+mod link;
+mod seen;
+mod store;
+mod webhook;
+mod chats;
+mod tracked;
 
+use config::Config;
 use event_manager::EventManager;
 use telegram::TelegramBot;
 use rocket_contrib::serve::StaticFiles;
@@ -67,11 +84,26 @@ fn main() {
         .apply()
         .unwrap();
 
-    let (event_manager, recv) = EventManager::new();
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        },
+    };
+    let address = config.socket_addr().unwrap();
+
+    let (event_manager, recv) = match EventManager::new(config.clone()) {
+        Ok(event_manager) => event_manager,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        },
+    };
     let events = Arc::new(Mutex::new(event_manager));
     let update_events = events.clone();
     let update = move || {
-        match update_events.lock().unwrap().update() {
+        match EventManager::update(&update_events) {
             Ok(_) => {
             },
             Err(e) => {
@@ -80,8 +112,10 @@ fn main() {
         }
     };
 
+    let bot_config = config.clone();
+    let bot_events = events.clone();
     thread::spawn(move || {
-        match TelegramBot::new().unwrap().run(recv) {
+        match TelegramBot::new(&bot_config, bot_events).and_then(|bot| bot.run(recv)) {
             Ok(_) => {
             },
             Err(e) => {
@@ -91,14 +125,32 @@ fn main() {
     });
 
     let mut scheduler = Scheduler::new();
-    scheduler.every(5.minutes()).run(update.clone());
+    scheduler.every(config.poll_interval.seconds()).run(update.clone());
     let _handle = scheduler.watch_thread(std::time::Duration::from_millis(500));
     thread::spawn(update);
 
-    rocket::ignite()
+    let mut rocket_config = rocket::Config::active().unwrap();
+    rocket_config.set_address(address.ip().to_string()).unwrap();
+    rocket_config.set_port(address.port());
+
+    rocket::custom(rocket_config)
         .register(catchers![content::not_found])
-        .mount("/", routes![content::index, content::feed, content::about])
+        .mount("/", routes![
+            content::index,
+            content::user,
+            content::repo,
+            content::feed,
+            content::rss,
+            content::json_feed,
+            content::leaderboard,
+            content::stats,
+            content::status,
+            content::about,
+        ])
+        .mount("/", routes![api::events, api::users, api::stats])
+        .mount("/", routes![webhook::github])
         .mount("/", StaticFiles::from("assets"))
         .manage(events)
+        .manage(config)
         .launch();
 }
@@ -0,0 +1,351 @@
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use rocket::http::uri::Uri;
+use std::fmt;
+
+use events::Event;
+
+const DEFAULT_PER_PAGE: usize = 30;
+const MAX_PER_PAGE: usize = 100;
+
+/// Filter and pagination parameters of event lists.
+///
+/// Empty or malformed parameters are ignored.
+#[derive(FromForm, Default)]
+pub struct EventQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    /// Only show events older than this cursor
+    pub before: Option<String>,
+    /// Only show events newer than this cursor
+    pub after: Option<String>,
+    /// Login of the user that caused the event
+    pub user: Option<String>,
+    /// Owner of the repository the event belongs to
+    pub owner: Option<String>,
+    /// First day to show events of, given as `YYYY-MM-DD`
+    pub since: Option<String>,
+    /// Last day to show events of, given as `YYYY-MM-DD`
+    pub until: Option<String>,
+    /// Text to search for in repository names and logins
+    pub q: Option<String>,
+}
+
+/// Position of an event in the history.
+///
+/// Events are ordered by creation time and id, so events created at the same
+/// time are neither skipped nor repeated when paging through them.
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: String,
+}
+
+impl Cursor {
+    pub fn of(event: &Event) -> Self {
+        Cursor {
+            created_at: event.created_at,
+            id: event.id.clone(),
+        }
+    }
+
+    /// Parses `<nanoseconds>_<id>`. Plain unix timestamps of older links
+    /// point to the start of that second.
+    fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(2, '_');
+        let time = parts.next()?.parse::<i64>().ok()?;
+
+        match parts.next() {
+            Some(id) if time >= 0 => Utc
+                .timestamp_opt(time / 1_000_000_000, (time % 1_000_000_000) as u32)
+                .single()
+                .map(|created_at| Cursor {
+                    created_at: created_at,
+                    id: id.to_string(),
+                }),
+            Some(_) => None,
+            None => Utc
+                .timestamp_opt(time, 0)
+                .single()
+                .map(|created_at| Cursor {
+                    created_at: created_at,
+                    id: String::new(),
+                }),
+        }
+    }
+
+    fn key(&self) -> (DateTime<Utc>, &str) {
+        (self.created_at, &self.id)
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}_{}", self.created_at.timestamp_nanos(), self.id)
+    }
+}
+
+fn key(event: &Event) -> (DateTime<Utc>, &str) {
+    (event.created_at, &event.id)
+}
+
+/// One page of events with links to its neighbours.
+pub struct Page<'a> {
+    /// Events of this page, newest first
+    pub events: Vec<&'a Event>,
+    /// Number of events matching the filters on all pages
+    pub total: usize,
+    pub newer: Option<String>,
+    pub older: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_ref()
+        .map(|value| value.trim())
+        .and_then(|value| if value.is_empty() { None } else { Some(value) })
+}
+
+fn parse_date(value: &Option<String>) -> Option<NaiveDate> {
+    non_empty(value).and_then(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok())
+}
+
+impl EventQuery {
+    pub fn page(&self) -> usize {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.per_page.unwrap_or(DEFAULT_PER_PAGE).max(1).min(MAX_PER_PAGE)
+    }
+
+    pub fn before(&self) -> Option<Cursor> {
+        non_empty(&self.before).and_then(Cursor::parse)
+    }
+
+    pub fn after(&self) -> Option<Cursor> {
+        non_empty(&self.after).and_then(Cursor::parse)
+    }
+
+    pub fn user(&self) -> Option<&str> {
+        non_empty(&self.user)
+    }
+
+    pub fn owner(&self) -> Option<&str> {
+        non_empty(&self.owner)
+    }
+
+    pub fn since(&self) -> Option<NaiveDate> {
+        parse_date(&self.since)
+    }
+
+    pub fn until(&self) -> Option<NaiveDate> {
+        parse_date(&self.until)
+    }
+
+    pub fn q(&self) -> Option<&str> {
+        non_empty(&self.q)
+    }
+
+    /// Whether any filter besides pagination is active.
+    pub fn is_filtered(&self) -> bool {
+        self.user().is_some()
+            || self.owner().is_some()
+            || self.since().is_some()
+            || self.until().is_some()
+            || self.q().is_some()
+    }
+
+    /// Whether the event passes all filters, regardless of the cursors.
+    pub fn matches(&self, event: &Event) -> bool {
+        if let Some(user) = self.user() {
+            if !event.actor.display_login.eq_ignore_ascii_case(user) {
+                return false;
+            }
+        }
+        if let Some(owner) = self.owner() {
+            if !event.repo.name.split('/').next().map_or(false, |name| name.eq_ignore_ascii_case(owner)) {
+                return false;
+            }
+        }
+        if let Some(since) = self.since() {
+            if event.created_at.naive_utc().date() < since {
+                return false;
+            }
+        }
+        if let Some(until) = self.until() {
+            if event.created_at.naive_utc().date() > until {
+                return false;
+            }
+        }
+        if let Some(q) = self.q() {
+            if !event.matches_term(q) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// The events of the current page and links to the pages next to it
+    /// below `path`.
+    pub fn paginate<'a>(&self, path: &str, events: &'a [Event]) -> Page<'a> {
+        let page = self.page();
+        let per_page = self.per_page();
+        let before = self.before();
+        let after = self.after();
+        let mut matching = events
+            .iter()
+            .filter(|event| self.matches(event))
+            .collect::<Vec<&Event>>();
+        matching.sort_by(|a, b| key(b).cmp(&key(a)));
+
+        // The `after` cursor shows the events right above it, so its page
+        // continues where the page it was taken from ended.
+        let first = match (&after, &before) {
+            (&Some(ref after), _) => matching
+                .iter()
+                .take_while(|event| key(event) > after.key())
+                .count()
+                .saturating_sub(per_page),
+            (&None, &Some(ref before)) => matching
+                .iter()
+                .take_while(|event| key(event) >= before.key())
+                .count(),
+            (&None, &None) => 0,
+        };
+        let start = first
+            .saturating_add((page - 1).saturating_mul(per_page))
+            .min(matching.len());
+        let end = start.saturating_add(per_page).min(matching.len());
+        let shown = matching[start..end].to_vec();
+
+        let newer = if page > 1 {
+            Some(self.url(path, page - 1, before.as_ref(), after.as_ref()))
+        } else if start > 0 {
+            Some(match shown.first() {
+                Some(event) => self.url(path, 1, None, Some(&Cursor::of(event))),
+                None => self.url(path, 1, None, None),
+            })
+        } else {
+            None
+        };
+        let older = if end < matching.len() {
+            shown.last().map(|event| self.url(path, 1, Some(&Cursor::of(event)), None))
+        } else {
+            None
+        };
+
+        Page {
+            events: shown,
+            total: matching.len(),
+            newer: newer,
+            older: older,
+        }
+    }
+
+    /// Builds a link to `path` with the current filters, the given page and
+    /// the given cursors.
+    pub fn url(&self, path: &str, page: usize, before: Option<&Cursor>, after: Option<&Cursor>) -> String {
+        let mut params = Vec::new();
+
+        if page > 1 {
+            params.push(format!("page={}", page));
+        }
+        if self.per_page.is_some() {
+            params.push(format!("per_page={}", self.per_page()));
+        }
+        if let Some(before) = before {
+            params.push(format!("before={}", Uri::percent_encode(&before.to_string())));
+        }
+        if let Some(after) = after {
+            params.push(format!("after={}", Uri::percent_encode(&after.to_string())));
+        }
+        for &(name, value) in [
+            ("user", self.user()),
+            ("owner", self.owner()),
+            ("since", non_empty(&self.since)),
+            ("until", non_empty(&self.until)),
+            ("q", self.q()),
+        ].iter() {
+            if let Some(value) = value {
+                params.push(format!("{}={}", name, Uri::percent_encode(value)));
+            }
+        }
+
+        if params.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, params.join("&"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use events::{Actor, Payload, Repository, Source};
+
+    fn event(id: &str, second: i64) -> Event {
+        Event {
+            id: id.to_string(),
+            actor: Actor {
+                display_login: "octocat".to_string(),
+                avatar_url: "https://avatars.githubusercontent.com/u/583231".to_string(),
+            },
+            repo: Repository {
+                name: "rust-lang/rust".to_string(),
+                metadata: None,
+            },
+            payload: Payload::Watch,
+            created_at: Utc.timestamp(1_570_000_000 + second, 0),
+            source: Source::User("octocat".to_string()),
+        }
+    }
+
+    fn ids<'a>(page: &Page<'a>) -> Vec<&'a str> {
+        page.events.iter().map(|event| event.id.as_str()).collect()
+    }
+
+    #[test]
+    fn pages_through_events_of_the_same_second() {
+        let events = vec![event("1", 0), event("2", 0), event("3", 0), event("4", 0), event("5", 1)];
+        let mut query = EventQuery {
+            per_page: Some(2),
+            ..EventQuery::default()
+        };
+
+        let page = query.paginate("/", &events);
+        assert_eq!(ids(&page), vec!["5", "4"]);
+        assert_eq!(page.total, 5);
+        assert!(page.newer.is_none());
+        assert_eq!(page.older, Some(format!("/?per_page=2&before={}", Cursor::of(&events[3]))));
+
+        query.before = Some(Cursor::of(&events[3]).to_string());
+        let page = query.paginate("/", &events);
+        assert_eq!(ids(&page), vec!["3", "2"]);
+        assert_eq!(page.newer, Some(format!("/?per_page=2&after={}", Cursor::of(&events[2]))));
+        assert_eq!(page.older, Some(format!("/?per_page=2&before={}", Cursor::of(&events[1]))));
+
+        query.before = Some(Cursor::of(&events[1]).to_string());
+        let page = query.paginate("/", &events);
+        assert_eq!(ids(&page), vec!["1"]);
+        assert!(page.older.is_none());
+
+        // going back from the second page shows the first one again
+        query.before = None;
+        query.after = Some(Cursor::of(&events[2]).to_string());
+        let page = query.paginate("/", &events);
+        assert_eq!(ids(&page), vec!["5", "4"]);
+        assert!(page.newer.is_none());
+    }
+
+    #[test]
+    fn takes_unix_timestamps_as_cursors() {
+        let events = vec![event("1", 0), event("2", 1), event("3", 2)];
+        let query = EventQuery {
+            before: Some((1_570_000_000 + 2).to_string()),
+            ..EventQuery::default()
+        };
+
+        assert_eq!(ids(&query.paginate("/", &events)), vec!["2", "1"]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde_json;
+
+use error::GabelnError;
+
+#[derive(Serialize, Deserialize)]
+struct SeenEntry {
+    id: String,
+    seen_at: DateTime<Utc>,
+}
+
+/// Append-only log of event ids that were already published.
+///
+/// Every line of the log file is a JSON object holding the id of a published
+/// event and the time it was marked as seen.
+pub struct SeenEvents {
+    path: PathBuf,
+    ids: HashSet<String>,
+    fresh: bool,
+}
+
+impl SeenEvents {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, GabelnError> {
+        let path = path.as_ref().to_path_buf();
+        let mut ids = HashSet::new();
+        let fresh = !path.exists();
+
+        if !fresh {
+            debug!("Loading seen events from {}", path.display());
+
+            let file = File::open(&path)
+                .map_err(|e| {
+                    error!("Failed to open seen events log: {}", e);
+                    GabelnError::FailedToReadSeenEvents
+                })?;
+
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|_| GabelnError::FailedToReadSeenEvents)?;
+
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                match serde_json::from_str::<SeenEntry>(&line) {
+                    Ok(entry) => {
+                        ids.insert(entry.id);
+                    },
+                    Err(_) => {
+                        warn!("Skipping malformed line in seen events log: {}", line);
+                    },
+                }
+            }
+
+            info!("Loaded {} seen events", ids.len());
+        }
+
+        Ok(Self {
+            path: path,
+            ids: ids,
+            fresh: fresh,
+        })
+    }
+
+    /// Whether no log existed yet, i.e. nothing was ever marked as seen.
+    pub fn is_fresh(&self) -> bool {
+        self.fresh
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    pub fn insert_all<'a, I: IntoIterator<Item = &'a str>>(&mut self, ids: I) -> Result<(), GabelnError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| {
+                error!("Failed to open seen events log: {}", e);
+                GabelnError::FailedToWriteSeenEvents
+            })?;
+        let now = Utc::now();
+
+        for id in ids {
+            if self.ids.contains(id) {
+                continue;
+            }
+
+            let line = serde_json::to_string(&SeenEntry { id: id.to_string(), seen_at: now })
+                .map_err(|_| GabelnError::FailedToWriteSeenEvents)?;
+            writeln!(file, "{}", line)
+                .map_err(|_| GabelnError::FailedToWriteSeenEvents)?;
+
+            self.ids.insert(id.to_string());
+        }
+
+        file.sync_data()
+            .map_err(|_| GabelnError::FailedToWriteSeenEvents)?;
+        self.fresh = false;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{BTreeSet, HashMap};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+use events::{Event, Payload};
+
+pub fn is_fork(event: &Event) -> bool {
+    match event.payload {
+        Payload::Fork { .. } => true,
+        _ => false,
+    }
+}
+
+pub fn forks<'a>(events: &'a [Event]) -> impl DoubleEndedIterator<Item = &'a Event> + 'a {
+    events.iter().filter(|event| is_fork(event))
+}
+
+/// The number of forks as "1 fork" or "n forks".
+pub fn fork_count(count: usize) -> String {
+    match count {
+        1 => "1 fork".to_string(),
+        count => format!("{} forks", count),
+    }
+}
+
+/// Sorts counts descending by count and ascending by name.
+fn ranked(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut counts = counts.into_iter().collect::<Vec<(String, usize)>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    counts
+}
+
+pub fn forks_per_user(events: &[Event]) -> Vec<(String, usize)> {
+    let mut counts = HashMap::new();
+
+    for event in forks(events) {
+        *counts.entry(event.actor.display_login.clone()).or_insert(0) += 1;
+    }
+
+    ranked(counts)
+}
+
+/// Time span a leaderboard is computed for, ending now.
+#[derive(Clone, Copy)]
+pub enum Window {
+    Week,
+    Month,
+    Year,
+    AllTime,
+}
+
+impl Window {
+    pub fn all() -> Vec<Window> {
+        vec![Window::Week, Window::Month, Window::Year, Window::AllTime]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            Window::Week => "Last 7 days",
+            Window::Month => "Last 30 days",
+            Window::Year => "Last year",
+            Window::AllTime => "All time",
+        }
+    }
+
+    fn start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match *self {
+            Window::Week => Some(now - Duration::days(7)),
+            Window::Month => Some(now - Duration::days(30)),
+            Window::Year => Some(now - Duration::days(365)),
+            Window::AllTime => None,
+        }
+    }
+}
+
+/// The given users ranked by their number of forks within the given window.
+///
+/// Organization and repository sources also collect forks of outsiders,
+/// which are left out.
+pub fn leaderboard(events: &[Event], users: &[String], window: Window) -> Vec<(String, usize)> {
+    let start = window.start(Utc::now());
+    let mut counts = HashMap::new();
+    let ranked_forks = forks(events)
+        .filter(|event| start.map_or(true, |start| event.created_at > start))
+        .filter(|event| users.iter().any(|user| user.eq_ignore_ascii_case(&event.actor.display_login)));
+
+    for event in ranked_forks {
+        *counts.entry(event.actor.display_login.clone()).or_insert(0) += 1;
+    }
+
+    ranked(counts)
+}
+
+pub fn forks_per_repository(events: &[Event]) -> Vec<(String, usize)> {
+    let mut counts = HashMap::new();
+
+    for event in forks(events) {
+        *counts.entry(event.repo.name.clone()).or_insert(0) += 1;
+    }
+
+    ranked(counts)
+}
+
+/// Forks per week, starting on Mondays, from the week of the first fork to
+/// the week of the last one. Weeks without forks are included.
+pub fn forks_per_week(events: &[Event]) -> Vec<(NaiveDate, usize)> {
+    let mut counts = HashMap::new();
+
+    for event in forks(events) {
+        let day = event.created_at.naive_utc().date();
+        let week = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+        *counts.entry(week).or_insert(0) += 1;
+    }
+
+    let first = match counts.keys().min() {
+        Some(first) => *first,
+        None => return Vec::new(),
+    };
+    let last = *counts.keys().max().unwrap();
+    let mut weeks = Vec::new();
+    let mut week = first;
+
+    while week <= last {
+        weeks.push((week, counts.get(&week).cloned().unwrap_or(0)));
+        week = week + Duration::weeks(1);
+    }
+
+    weeks
+}
+
+/// Consecutive days on which a user forked at least one repository.
+pub struct Streak {
+    pub login: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl Streak {
+    pub fn days(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+}
+
+/// The longest streak of every user, longest first.
+pub fn longest_streaks(events: &[Event]) -> Vec<Streak> {
+    let mut days = HashMap::new();
+
+    for event in forks(events) {
+        days.entry(event.actor.display_login.clone())
+            .or_insert_with(BTreeSet::new)
+            .insert(event.created_at.naive_utc().date());
+    }
+
+    let mut streaks = days
+        .into_iter()
+        .filter_map(|(login, days)| {
+            let mut longest: Option<(NaiveDate, NaiveDate)> = None;
+            let mut current: Option<(NaiveDate, NaiveDate)> = None;
+
+            for day in days {
+                current = match current {
+                    Some((start, end)) if end.succ() == day => Some((start, day)),
+                    _ => Some((day, day)),
+                };
+                let (start, end) = current.unwrap();
+                if longest.map_or(true, |(longest_start, longest_end)| end - start > longest_end - longest_start) {
+                    longest = current;
+                }
+            }
+
+            longest.map(|(start, end)| Streak {
+                login: login,
+                start: start,
+                end: end,
+            })
+        })
+        .collect::<Vec<Streak>>();
+    streaks.sort_by(|a, b| b.days().cmp(&a.days()).then_with(|| a.login.cmp(&b.login)));
+
+    streaks
+}
+
+/// The day with the most forks, the earlier one on ties.
+pub fn busiest_day(events: &[Event]) -> Option<(NaiveDate, usize)> {
+    let mut counts = HashMap::new();
+
+    for event in forks(events) {
+        *counts.entry(event.created_at.naive_utc().date()).or_insert(0) += 1;
+    }
+
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+}
@@ -0,0 +1,199 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json;
+
+use error::GabelnError;
+use events::Event;
+
+/// History of all events ever collected, keyed by `Event::id`.
+///
+/// The history is stored as a JSON array and only shrinks when events are
+/// pruned explicitly.
+pub struct EventStore {
+    path: PathBuf,
+    events: HashMap<String, Event>,
+    fingerprints: HashSet<String>,
+}
+
+impl EventStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, GabelnError> {
+        let path = path.as_ref().to_path_buf();
+        let mut events = HashMap::new();
+
+        if path.exists() {
+            debug!("Loading event history from {}", path.display());
+
+            let file = File::open(&path)
+                .map_err(|e| {
+                    error!("Failed to open event history: {}", e);
+                    GabelnError::FailedToReadEventStore
+                })?;
+            let stored = serde_json::from_reader::<_, Vec<Event>>(BufReader::new(file))
+                .map_err(|e| {
+                    error!("Failed to parse event history: {}", e);
+                    GabelnError::FailedToReadEventStore
+                })?;
+
+            for event in stored {
+                events.insert(event.id.clone(), event);
+            }
+
+            info!("Loaded {} events from history", events.len());
+        }
+
+        let fingerprints = events
+            .values()
+            .filter_map(|event: &Event| event.fingerprint())
+            .collect();
+
+        Ok(Self {
+            path: path,
+            events: events,
+            fingerprints: fingerprints,
+        })
+    }
+
+    /// Whether the event or another copy of the same fork is stored.
+    pub fn contains(&self, event: &Event) -> bool {
+        self.events.contains_key(&event.id)
+            || event.fingerprint().map_or(false, |fingerprint| self.fingerprints.contains(&fingerprint))
+    }
+
+    /// Adds all events that are not stored yet and returns the new ones.
+    ///
+    /// Events older than `retention_days` are skipped, as they would be
+    /// pruned right away.
+    pub fn merge(&mut self, events: Vec<Event>, retention_days: Option<u32>) -> Vec<Event> {
+        let horizon = retention_days.map(horizon);
+        let mut added = Vec::new();
+
+        for event in events {
+            if self.events.contains_key(&event.id) || horizon.map_or(false, |horizon| event.created_at <= horizon) {
+                continue;
+            }
+            if let Some(fingerprint) = event.fingerprint() {
+                if !self.fingerprints.insert(fingerprint) {
+                    continue;
+                }
+            }
+
+            self.events.insert(event.id.clone(), event.clone());
+            added.push(event);
+        }
+
+        added
+    }
+
+    /// Removes all events older than the given number of days.
+    pub fn prune(&mut self, retention_days: u32) -> usize {
+        let horizon = horizon(retention_days);
+        let before = self.events.len();
+
+        self.events.retain(|_, event| event.created_at > horizon);
+        self.fingerprints = self.events
+            .values()
+            .filter_map(|event| event.fingerprint())
+            .collect();
+
+        before - self.events.len()
+    }
+
+    pub fn save(&self) -> Result<(), GabelnError> {
+        write_json_atomically(&self.path, &self.events())
+            .map_err(|e| {
+                error!("Failed to write event history: {}", e);
+                GabelnError::FailedToWriteEventStore
+            })
+    }
+
+    /// All stored events sorted by their creation time.
+    pub fn events(&self) -> Vec<Event> {
+        let mut events = self.events.values().cloned().collect::<Vec<Event>>();
+        events.sort_unstable_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+
+        events
+    }
+}
+
+/// The creation time up to which events are older than `retention_days`.
+fn horizon(retention_days: u32) -> DateTime<Utc> {
+    Utc::now() - Duration::days(retention_days as i64)
+}
+
+/// Writes the value as JSON to a temporary file next to `path` and moves it
+/// into place, so a crash never leaves a truncated file behind.
+pub fn write_json_atomically<P: AsRef<Path>, T: Serialize>(path: P, value: &T) -> io::Result<()> {
+    let path = path.as_ref();
+    let tmp_path = path.with_extension("tmp");
+
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, value)?;
+        writer.flush()?;
+    }
+
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    use events::{Actor, Forkee, Payload, Repository, Source};
+
+    fn store(name: &str) -> EventStore {
+        EventStore::open(env::temp_dir().join(format!("gabeln-{}-{}.json", name, process::id()))).unwrap()
+    }
+
+    fn fork(id: &str, forkee_id: u64, created_at: DateTime<Utc>) -> Event {
+        Event {
+            id: id.to_string(),
+            actor: Actor {
+                display_login: "octocat".to_string(),
+                avatar_url: "https://avatars.githubusercontent.com/u/583231".to_string(),
+            },
+            repo: Repository {
+                name: "rust-lang/rust".to_string(),
+                metadata: None,
+            },
+            payload: Payload::Fork {
+                forkee: Forkee {
+                    id: Some(forkee_id),
+                    full_name: "octocat/rust".to_string(),
+                    html_url: "https://github.com/octocat/rust".to_string(),
+                },
+            },
+            created_at: created_at,
+            source: Source::User("octocat".to_string()),
+        }
+    }
+
+    #[test]
+    fn merges_copies_of_the_same_fork() {
+        let mut store = store("fingerprints");
+        let now = Utc::now();
+
+        assert_eq!(store.merge(vec![fork("webhook-abc", 1, now)], None).len(), 1);
+        assert_eq!(store.merge(vec![fork("123", 1, now)], None).len(), 0);
+        // forked again after the first fork was deleted
+        assert_eq!(store.merge(vec![fork("456", 2, now)], None).len(), 1);
+        assert_eq!(store.events().len(), 2);
+    }
+
+    #[test]
+    fn skips_events_older_than_the_retention() {
+        let mut store = store("retention");
+        let now = Utc::now();
+
+        let added = store.merge(vec![fork("1", 1, now - Duration::days(40)), fork("2", 2, now)], Some(30));
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].id, "2");
+        assert_eq!(store.prune(30), 0);
+    }
+}
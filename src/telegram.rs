@@ -1,16 +1,26 @@
-use std::env;
 use std::vec::Vec;
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
-use futures::{Future, Stream, future::{ok, lazy}, sync::mpsc::Receiver};
+use std::thread;
+use std::time::Duration;
+use futures::{Future, Stream, future::{ok, lazy}, sync::mpsc::UnboundedReceiver};
+use tokio::executor::current_thread;
+use chrono::Utc;
+use rand::{thread_rng, seq::SliceRandom};
 use telegram_bot_fork::{
+    AnswerInlineQuery,
     Api,
     CanReplySendMessage,
     CanSendDocument,
     CanSendMessage,
     CanGetChatAdministrators,
+    ChatId,
     GetMe,
     GetChatAdministrators,
+    InlineQuery,
+    InlineQueryResult,
+    InlineQueryResultArticle,
+    InlineQueryResultGif,
+    InputTextMessageContent,
     Message,
     MessageChat,
     MessageChat::Private,
@@ -18,23 +28,32 @@ use telegram_bot_fork::{
     MessageChat::Supergroup,
     MessageKind,
     ParseMode,
+    Request,
     Update,
     UpdateKind,
     User,
 };
+use chats::ChatStore;
 use error::GabelnError;
-use events::Event;
-use giphy::Giphy;
+use event_manager::EventManager;
+use events::{Event, Payload, RepositoryMetadata};
+use giphy::{Giphy, GiphyOriginal};
+use config::Config;
+use stats;
 use regex::Regex;
 
 #[derive(Clone)]
 struct InnerTelegramBot {
     api: Api,
-    chats: HashMap<MessageChat, Vec<User>>,
-    active_chats: Vec<MessageChat>,
+    chats: Arc<Mutex<ChatStore>>,
     me: User,
     giphy: Giphy,
+    /// Gifs offered in inline mode, refreshed in the background
+    gifs: Arc<Mutex<Vec<GiphyOriginal>>>,
     command_re: Regex,
+    events: Arc<Mutex<EventManager>>,
+    /// Users allowed to change the tracked users in any chat
+    admins: Vec<i64>,
 }
 
 #[derive(Clone)]
@@ -47,13 +66,26 @@ enum BotUpdate {
     Event(Event),
 }
 
+/// Number of forks listed by default.
+const DEFAULT_FORKS: usize = 5;
+/// Maximum number of forks listed in one message.
+const MAX_FORKS: usize = 20;
+/// Maximum number of results of an inline query, Telegram allows up to 50.
+const MAX_INLINE_RESULTS: usize = 20;
+/// Seconds Telegram may cache the results of an inline query.
+const INLINE_CACHE_SECONDS: i64 = 60;
+/// Seconds between two refreshes of the gifs offered in inline mode.
+const GIF_REFRESH_SECONDS: u64 = 3600;
+
 type BotFuture<'a> = Box<Future<Item = (), Error = GabelnError> + 'a>;
 
 impl TelegramBot {
-    pub fn new() -> Result<TelegramBot, GabelnError> {
-        let giphy = Giphy::new()?;
-        let token = env::var("TELEGRAM_BOT_TOKEN")
-            .map_err(|_| GabelnError::NoTelegramBotToken)?;
+    pub fn new(config: &Config, events: Arc<Mutex<EventManager>>) -> Result<TelegramBot, GabelnError> {
+        let giphy = Giphy::new(&config.giphy)?;
+        let chats = ChatStore::open(&config.telegram.chats_file)?;
+        let token = config.telegram.bot_token
+            .clone()
+            .ok_or(GabelnError::NoTelegramBotToken)?;
         let api = Api::new(token)
             .map_err(|_| GabelnError::FailedToCreateTelegramBot)?;
 
@@ -61,13 +93,18 @@ impl TelegramBot {
             .block_on(api.send(GetMe))
             .map_err(|_| GabelnError::FailedToGetOwnUser)?;
 
+        let gifs = Arc::new(Mutex::new(Vec::new()));
+        refresh_gifs(giphy.clone(), gifs.clone());
+
         let inner = InnerTelegramBot {
             api: api,
-            chats: HashMap::new(),
-            active_chats: Vec::new(),
+            chats: Arc::new(Mutex::new(chats)),
             me: me,
             giphy: giphy,
-            command_re: Regex::new(r"^/(\w+)(?:@(\w+))?$").unwrap(),
+            gifs: gifs,
+            command_re: Regex::new(r"^/(\w+)(?:@(\w+))?(?:\s+(.*?))?\s*$").unwrap(),
+            events: events,
+            admins: config.telegram.admins.clone(),
         };
 
         Ok(Self {
@@ -75,7 +112,7 @@ impl TelegramBot {
         })
     }
 
-    pub fn run<'a>(mut self, recv: Receiver<Event>) -> Result<(), GabelnError> {
+    pub fn run<'a>(mut self, recv: UnboundedReceiver<Event>) -> Result<(), GabelnError> {
         let update_stream = self.inner.lock().unwrap().api.stream()
             .map(|update| BotUpdate::Update(update))
             .map_err(|_| GabelnError::FailedToListenForTelegramMessages);
@@ -103,13 +140,15 @@ impl TelegramBot {
                 )
             },
             BotUpdate::Event(event) => {
-                self.send_text(format!(
-                    "*{}* forked _{}_ at [{}]({})!",
-                    event.actor.display_login,
-                    event.repo.name,
-                    event.payload.forkee.clone().unwrap().full_name,
-                    event.payload.forkee.clone().unwrap().html_url,
-                ))
+                let recipients = self.inner
+                    .lock()
+                    .unwrap()
+                    .chats
+                    .lock()
+                    .unwrap()
+                    .recipients(&event);
+
+                self.send_text(recipients, event_text(&event))
             },
         }
     }
@@ -124,7 +163,13 @@ impl TelegramBot {
         };
 
         if let Some(chat) = chat {
-            if !inner.chats.contains_key(&chat) {
+            let outdated = inner.chats
+                .lock()
+                .unwrap()
+                .get(chat.id())
+                .map_or(true, |state| state.admins_outdated(Utc::now()));
+
+            if outdated {
                 let get_admins: Option<GetChatAdministrators> = match chat {
                     Group(ref group) => Some(group.get_administrators()),
                     Supergroup(ref group) => Some(group.get_administrators()),
@@ -132,25 +177,39 @@ impl TelegramBot {
                 };
 
                 if let Some(get_admins) = get_admins {
-                    let inner_arc = self.inner.clone();
+                    let chats = inner.chats.clone();
 
                     debug!("Trying to get admins...");
 
+                    // Failures only delay the admin check, they must not stop the bot.
                     return Box::new(
                         inner.api
                             .send(get_admins)
-                            .map(move |admins| {
-                                let mut inner = inner_arc.lock().unwrap();
-
-                                inner.chats.insert(
-                                    chat,
-                                    admins
+                            .then(move |admins| -> Result<(), GabelnError> {
+                                let admins = match admins {
+                                    Ok(admins) => admins
                                         .iter()
-                                        .map(|member| member.user.clone())
-                                        .collect(),
-                                );
+                                        .map(|member| i64::from(member.user.id))
+                                        .collect::<Vec<i64>>(),
+                                    Err(e) => {
+                                        warn!("Failed to get the admins of chat {}: {}", chat.id(), e);
+                                        return Ok(());
+                                    },
+                                };
+
+                                let saved = chats
+                                    .lock()
+                                    .unwrap()
+                                    .update(chat.id(), |state| {
+                                        state.admins = Some(admins);
+                                        state.admins_fetched_at = Some(Utc::now());
+                                    });
+                                if let Err(e) = saved {
+                                    error!("Failed to save the admins of chat {}: {}", chat.id(), e);
+                                }
+
+                                Ok(())
                             })
-                            .map_err(|_| GabelnError::FailedToStartBot)
                     );
                 }
             }
@@ -163,13 +222,13 @@ impl TelegramBot {
         if let UpdateKind::Message(ref message) = update.kind {
             match message.kind {
                 MessageKind::LeftChatMember {ref data, ..} => {
-                    let inner_arc = self.inner.clone();
-                    let mut inner = inner_arc.lock().unwrap();
+                    let inner = self.inner.lock().unwrap();
 
                     if data.id == inner.me.id {
                         info!("Stopping bot in chat: {}", &message.chat.id());
-                        inner.active_chats.remove_item(&message.chat);
-                        inner.chats.remove(&message.chat);
+                        if let Err(e) = inner.chats.lock().unwrap().remove(message.chat.id()) {
+                            error!("{:?}", e);
+                        }
                     }
 
                     Box::new(ok(())) as BotFuture<'a>
@@ -183,16 +242,46 @@ impl TelegramBot {
                         .command(content.as_str(), &message.chat);
 
                     match parsed {
-                        Some("start") => {
+                        Some(("start", _)) => {
                             self.cmd_start(message)
                         },
-                        Some("stop") => {
+                        Some(("stop", _)) => {
                             self.cmd_stop(message)
                         },
-                        Some("help") => {
+                        Some(("help", _)) => {
                             self.cmd_help(message)
                         },
-                        Some(command) => {
+                        Some(("leaderboard", _)) => {
+                            self.cmd_leaderboard(message)
+                        },
+                        Some(("follow", login)) => {
+                            self.cmd_follow(message, login.to_owned())
+                        },
+                        Some(("unfollow", login)) => {
+                            self.cmd_unfollow(message, login.to_owned())
+                        },
+                        Some(("latest", count)) => {
+                            self.cmd_latest(message, count.to_owned())
+                        },
+                        Some(("search", term)) => {
+                            self.cmd_search(message, term.to_owned())
+                        },
+                        Some(("user", login)) => {
+                            self.cmd_user(message, login.to_owned())
+                        },
+                        Some(("track", login)) => {
+                            self.cmd_track(message, login.to_owned())
+                        },
+                        Some(("untrack", login)) => {
+                            self.cmd_untrack(message, login.to_owned())
+                        },
+                        Some(("following", _)) => {
+                            self.cmd_following(message)
+                        },
+                        Some(("repofilter", filter)) => {
+                            self.cmd_repofilter(message, filter.to_owned())
+                        },
+                        Some((command, _)) => {
                             self.cmd_unknown(message, command.to_owned())
                         },
                         None => {
@@ -204,22 +293,99 @@ impl TelegramBot {
                     Box::new(ok(())) as BotFuture<'a>
                 },
             }
+        } else if let UpdateKind::InlineQuery(ref query) = update.kind {
+            self.inline_query(query.clone())
         } else {
             Box::new(ok(())) as BotFuture<'a>
         }
     }
 
-    fn send_text<'a>(&self, msg: String) -> BotFuture<'a> {
+    fn inline_query<'a>(&self, query: InlineQuery) -> BotFuture<'a> {
         let inner_arc = self.inner.clone();
 
         Box::new(lazy(move || {
             let inner = inner_arc.lock().unwrap();
+            let term = query.query.trim();
+            let mut results: Vec<InlineQueryResult> = Vec::new();
 
-            for chat in inner.active_chats.iter() {
-                inner.api.spawn(chat.text(&msg).parse_mode(ParseMode::Markdown));
+            debug!("Answering inline query {}", term);
+            if let Some(gif) = inner.gifs.lock().unwrap().choose(&mut thread_rng()) {
+                results.push(InlineQueryResult::InlineQueryResultGif(InlineQueryResultGif {
+                    id: "gif".to_string(),
+                    gif_url: gif.url.clone(),
+                    gif_width: gif.width.parse().ok(),
+                    gif_height: gif.height.parse().ok(),
+                    gif_duration: None,
+                    thumb_url: gif.url.clone(),
+                    title: Some("Random fork gif".to_string()),
+                    caption: None,
+                    parse_mode: None,
+                    reply_markup: None,
+                    input_message_content: None,
+                }));
             }
 
-            inner.send_gif(None)
+            {
+                let event_manager = inner.events.lock().unwrap();
+
+                for event in stats::forks(&event_manager.events).rev() {
+                    if results.len() >= MAX_INLINE_RESULTS {
+                        break;
+                    }
+
+                    let forkee = match event.payload {
+                        Payload::Fork { ref forkee } => forkee,
+                        _ => continue,
+                    };
+                    if !term.is_empty() && !event.matches_term(term) {
+                        continue;
+                    }
+
+                    let description = match event.repo.metadata.as_ref().and_then(|metadata| metadata.description.clone()) {
+                        Some(description) => format!("{}\n{}", forkee.full_name, description),
+                        None => forkee.full_name.clone(),
+                    };
+
+                    results.push(
+                        InlineQueryResultArticle::new(
+                            event.id.clone(),
+                            format!("{} forked {}", event.actor.display_login, event.repo.name),
+                            text_content(event_text(event)),
+                        )
+                            .description(description)
+                            .url(forkee.html_url.clone())
+                            .thumb_url(event.actor.avatar_url.clone())
+                            .clone()
+                            .into()
+                    );
+                }
+            }
+
+            let mut answer = AnswerInlineQuery::new(query.id, results);
+            answer.cache_time(INLINE_CACHE_SECONDS);
+            inner.api.spawn(answer);
+
+            Ok(())
+        }))
+    }
+
+    fn send_text<'a>(&self, chats: Vec<ChatId>, msg: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+
+            if chats.is_empty() {
+                return Ok(());
+            }
+
+            for chat in chats.iter() {
+                let mut request = chat.text(msg.clone());
+                request.parse_mode(ParseMode::Html);
+                inner.send_to(*chat, request);
+            }
+
+            inner.send_gif(&chats)
         }))
     }
 
@@ -228,9 +394,10 @@ impl TelegramBot {
         Box::new(lazy(move || {
             let inner = inner_arc.lock().unwrap();
 
-            if inner.active_chats.contains(&message.chat) && content.contains("gabeln.jetzt") {
+            let active = inner.chats.lock().unwrap().is_active(message.chat.id());
+            if active && content.contains("gabeln.jetzt") {
                 info!("User {} requested gif", message.from.first_name);
-                return inner.send_gif(Some(message.chat));
+                return inner.send_gif(&[message.chat.id()]);
             }
 
             Ok(())
@@ -242,17 +409,18 @@ impl TelegramBot {
 
         Box::new(lazy(move || {
             debug!("Trying to start bot!");
-            let mut inner = inner_arc.lock().unwrap();
+            let inner = inner_arc.lock().unwrap();
 
             if inner.check_admin(&message) {
                 debug!("User is authorized!");
+                let mut chats = inner.chats.lock().unwrap();
 
-                if inner.active_chats.contains(&message.chat) {
+                if chats.is_active(message.chat.id()) {
                     inner.api.spawn(message.text_reply("Bot already running in this chat!"));
                 } else {
                     info!("Starting bot in new chat: {}", message.chat.id());
-                    inner.api.spawn(message.text_reply("Starting bot in this chat!"));
-                    inner.active_chats.push(message.chat);
+                    let saved = chats.update(message.chat.id(), |state| state.active = true);
+                    inner.reply_saved(&message, saved, "Starting bot in this chat!");
                 }
             }
 
@@ -264,14 +432,16 @@ impl TelegramBot {
         let inner_arc = self.inner.clone();
 
         Box::new(lazy(move || {
-            let mut inner = inner_arc.lock().unwrap();
+            let inner = inner_arc.lock().unwrap();
 
             debug!("Trying to stop bot!");
             if inner.check_admin(&message) {
-                if inner.active_chats.contains(&message.chat) {
+                let mut chats = inner.chats.lock().unwrap();
+
+                if chats.is_active(message.chat.id()) {
                     info!("Stopping bot in chat: {}", message.chat.id());
-                    inner.active_chats.remove_item(&message.chat);
-                    inner.api.spawn(message.text_reply("Stopping bot in this chat!"));
+                    let saved = chats.update(message.chat.id(), |state| state.active = false);
+                    inner.reply_saved(&message, saved, "Stopping bot in this chat!");
                 } else {
                     inner.api.spawn(message.text_reply("Bot is not running in this chat!"));
                 }
@@ -291,9 +461,20 @@ impl TelegramBot {
                 message.text_reply(format!(
                     "*gabeln.jetzt Telegram Bot*
 
-This telegram bot will send messages when a configured github user forks a repository on [github.com](https://github.com). To configure your user for this bot please ask your server administrator.
+This telegram bot will send messages when a tracked github user forks a repository on [github.com](https://github.com). Chat administrators can track and untrack users with the commands below.
+
+This bot also sends fork gifs when any message in this chat contains the keyword `gabeln.jetzt`. Mention the bot in any chat to share a recent fork or a random fork gif.
 
-This bot also sends fork gifs when any message in this chat contains the keyword `gabeln.jetzt`.
+/leaderboard - Show the tracked users with the most forks
+/latest [n] - Show the latest forks
+/search <term> - Find forks by repository or user
+/user <user> - Show the fork history of a user
+/follow <user> - Only send events of the followed users
+/unfollow <user> - Stop following a user
+/following - Show the filters of this chat
+/track <user> - Track the forks of a github user
+/untrack <user> - Stop tracking a github user
+/repofilter <regex> - Only send events of matching repositories, no regex removes the filter
 
 Happy forking!
 "
@@ -304,6 +485,357 @@ Happy forking!
         }))
     }
 
+    fn cmd_leaderboard<'a>(&self, message: Message) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+            let mut text = "<b>Leaderboard</b>\n".to_string();
+
+            {
+                let event_manager = inner.events.lock().unwrap();
+
+                for window in stats::Window::all() {
+                    text.push_str(&format!("\n<i>{}</i>\n", window.label()));
+
+                    let ranking = stats::leaderboard(&event_manager.events, event_manager.users(), window);
+                    if ranking.is_empty() {
+                        text.push_str("No forks yet.\n");
+                    }
+                    for (rank, (login, count)) in ranking.into_iter().take(5).enumerate() {
+                        text.push_str(&format!(
+                            "{}. <b>{}</b> with {}\n",
+                            rank + 1,
+                            escape_html(&login),
+                            stats::fork_count(count),
+                        ));
+                    }
+                }
+            }
+
+            inner.api.spawn(message.text_reply(text).parse_mode(ParseMode::Html));
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_latest<'a>(&self, message: Message, count: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+            let count = match count.as_str() {
+                "" => DEFAULT_FORKS,
+                count => match count.parse::<usize>() {
+                    Ok(count) if count > 0 => count.min(MAX_FORKS),
+                    _ => {
+                        inner.api.spawn(message.text_reply("Usage: /latest [n]"));
+                        return Ok(());
+                    },
+                },
+            };
+
+            let text = {
+                let event_manager = inner.events.lock().unwrap();
+                let forks = stats::forks(&event_manager.events)
+                    .rev()
+                    .take(count)
+                    .map(fork_line)
+                    .collect::<Vec<String>>();
+
+                if forks.is_empty() {
+                    "No forks yet.".to_string()
+                } else {
+                    format!("<b>Latest forks</b>\n\n{}", forks.join("\n"))
+                }
+            };
+
+            inner.api.spawn(message.text_reply(text).parse_mode(ParseMode::Html).disable_preview());
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_search<'a>(&self, message: Message, term: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+
+            if term.is_empty() {
+                inner.api.spawn(message.text_reply("Usage: /search <term>"));
+                return Ok(());
+            }
+
+            let text = {
+                let event_manager = inner.events.lock().unwrap();
+                let forks = stats::forks(&event_manager.events)
+                    .rev()
+                    .filter(|event| event.matches_term(&term))
+                    .collect::<Vec<&Event>>();
+
+                if forks.is_empty() {
+                    format!("No forks matching {} found.", escape_html(&term))
+                } else {
+                    format!(
+                        "<b>{} matching {}</b>\n\n{}",
+                        stats::fork_count(forks.len()),
+                        escape_html(&term),
+                        forks
+                            .into_iter()
+                            .take(MAX_FORKS)
+                            .map(fork_line)
+                            .collect::<Vec<String>>()
+                            .join("\n"),
+                    )
+                }
+            };
+
+            inner.api.spawn(message.text_reply(text).parse_mode(ParseMode::Html).disable_preview());
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_user<'a>(&self, message: Message, login: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+
+            if login.is_empty() {
+                inner.api.spawn(message.text_reply("Usage: /user <user>"));
+                return Ok(());
+            }
+
+            let text = {
+                let event_manager = inner.events.lock().unwrap();
+                let forks = stats::forks(&event_manager.events)
+                    .filter(|event| event.actor.display_login.eq_ignore_ascii_case(&login))
+                    .cloned()
+                    .collect::<Vec<Event>>();
+
+                match (forks.first(), forks.last()) {
+                    (Some(first), Some(last)) => {
+                        let repositories = stats::forks_per_repository(&forks)
+                            .into_iter()
+                            .take(3)
+                            .map(|(name, count)| format!("{} ({})", escape_html(&name), count))
+                            .collect::<Vec<String>>();
+
+                        format!(
+                            "<b>{}</b> has {} between {} and {}.\nMost forked: {}\n\n{}",
+                            escape_html(&last.actor.display_login),
+                            stats::fork_count(forks.len()),
+                            first.created_at.format("%Y-%m-%d"),
+                            last.created_at.format("%Y-%m-%d"),
+                            repositories.join(", "),
+                            forks
+                                .iter()
+                                .rev()
+                                .take(DEFAULT_FORKS)
+                                .map(fork_line)
+                                .collect::<Vec<String>>()
+                                .join("\n"),
+                        )
+                    },
+                    _ => format!("No forks of {} found.", escape_html(&login)),
+                }
+            };
+
+            inner.api.spawn(message.text_reply(text).parse_mode(ParseMode::Html).disable_preview());
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_track<'a>(&self, message: Message, login: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let (api, events) = {
+                let inner = inner_arc.lock().unwrap();
+
+                if !is_github_login(&login) {
+                    inner.api.spawn(message.text_reply("Usage: /track <github-login>"));
+                    return Ok(());
+                }
+                if !inner.check_tracking_admin(&message) {
+                    return Ok(());
+                }
+
+                (inner.api.clone(), inner.events.clone())
+            };
+
+            // looking up the user takes a request to GitHub, so no lock is held
+            match EventManager::track(&events, &login) {
+                Ok(Some(login)) => {
+                    api.spawn(message.text_reply(format!(
+                        "Tracking {}! Their forks show up with the next update.", login
+                    )));
+                },
+                Ok(None) => {
+                    api.spawn(message.text_reply(format!("{} is already tracked!", login)));
+                },
+                Err(e) => {
+                    warn!("Failed to track {}: {}", login, e);
+                    api.spawn(message.text_reply(e.to_string()));
+                },
+            }
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_untrack<'a>(&self, message: Message, login: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+
+            if !is_github_login(&login) {
+                inner.api.spawn(message.text_reply("Usage: /untrack <github-login>"));
+                return Ok(());
+            }
+
+            if inner.check_tracking_admin(&message) {
+                let untracked = inner.events.lock().unwrap().untrack(&login);
+
+                match untracked {
+                    Ok(Some(login)) => {
+                        inner.api.spawn(message.text_reply(format!("Stopped tracking {}!", login)));
+                    },
+                    Ok(None) => {
+                        inner.api.spawn(message.text_reply(format!("{} is not tracked!", login)));
+                    },
+                    Err(e) => {
+                        warn!("Failed to untrack {}: {}", login, e);
+                        inner.api.spawn(message.text_reply(e.to_string()));
+                    },
+                }
+            }
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_follow<'a>(&self, message: Message, login: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+
+            if !is_github_login(&login) {
+                inner.api.spawn(message.text_reply("Usage: /follow <github-login>"));
+                return Ok(());
+            }
+
+            if inner.check_admin(&message) {
+                let mut chats = inner.chats.lock().unwrap();
+                let following = chats
+                    .get(message.chat.id())
+                    .map_or(false, |state| state.following.iter().any(|followed| followed.eq_ignore_ascii_case(&login)));
+
+                if following {
+                    inner.api.spawn(message.text_reply(format!("Already following {}!", login)));
+                } else {
+                    info!("Following {} in chat {}", login, message.chat.id());
+                    let saved = chats.update(message.chat.id(), |state| state.following.push(login.clone()));
+                    inner.reply_saved(&message, saved, format!("Following {}!", login));
+                }
+            }
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_unfollow<'a>(&self, message: Message, login: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+
+            if login.is_empty() {
+                inner.api.spawn(message.text_reply("Usage: /unfollow <user>"));
+                return Ok(());
+            }
+
+            if inner.check_admin(&message) {
+                let mut chats = inner.chats.lock().unwrap();
+                let following = chats
+                    .get(message.chat.id())
+                    .map_or(false, |state| state.following.iter().any(|followed| followed.eq_ignore_ascii_case(&login)));
+
+                if following {
+                    info!("Unfollowing {} in chat {}", login, message.chat.id());
+                    let saved = chats.update(message.chat.id(), |state| {
+                        state.following.retain(|followed| !followed.eq_ignore_ascii_case(&login))
+                    });
+                    inner.reply_saved(&message, saved, format!("Stopped following {}!", login));
+                } else {
+                    inner.api.spawn(message.text_reply(format!("Not following {}!", login)));
+                }
+            }
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_following<'a>(&self, message: Message) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+            let (following, repo_filter) = inner.chats
+                .lock()
+                .unwrap()
+                .get(message.chat.id())
+                .map_or((Vec::new(), None), |state| (state.following.clone(), state.repo_filter.clone()));
+
+            let mut text = if following.is_empty() {
+                "This chat receives the events of all users.".to_string()
+            } else {
+                format!("This chat follows {}.", following.join(", "))
+            };
+            if let Some(repo_filter) = repo_filter {
+                text.push_str(&format!("\nOnly repositories matching {} are included.", repo_filter));
+            }
+
+            inner.api.spawn(message.text_reply(text));
+
+            Ok(())
+        }))
+    }
+
+    fn cmd_repofilter<'a>(&self, message: Message, filter: String) -> BotFuture<'a> {
+        let inner_arc = self.inner.clone();
+
+        Box::new(lazy(move || {
+            let inner = inner_arc.lock().unwrap();
+
+            if let Err(e) = Regex::new(&filter) {
+                inner.api.spawn(message.text_reply(format!("Invalid regular expression: {}", e)));
+                return Ok(());
+            }
+
+            if inner.check_admin(&message) {
+                let mut chats = inner.chats.lock().unwrap();
+
+                if filter.is_empty() {
+                    info!("Removing repository filter of chat {}", message.chat.id());
+                    let saved = chats.update(message.chat.id(), |state| state.repo_filter = None);
+                    inner.reply_saved(&message, saved, "Removed the repository filter!");
+                } else {
+                    info!("Setting repository filter of chat {} to {}", message.chat.id(), filter);
+                    let saved = chats.update(message.chat.id(), |state| state.repo_filter = Some(filter.clone()));
+                    inner.reply_saved(&message, saved, format!("Only sending events of repositories matching {}!", filter));
+                }
+            }
+
+            Ok(())
+        }))
+    }
+
     fn cmd_unknown<'a>(&self, message: Message, command: String) -> BotFuture<'a> {
         let inner_arc = self.inner.clone();
 
@@ -318,19 +850,151 @@ Happy forking!
     }
 }
 
+/// Keeps the gifs offered in inline mode fresh without fetching them while
+/// answering a query.
+fn refresh_gifs(giphy: Giphy, gifs: Arc<Mutex<Vec<GiphyOriginal>>>) {
+    thread::spawn(move || loop {
+        match giphy.get_gifs() {
+            Ok(fetched) => {
+                debug!("Fetched {} gifs for inline queries", fetched.len());
+                *gifs.lock().unwrap() = fetched;
+            },
+            Err(e) => warn!("Failed to refresh the gifs for inline queries: {:?}", e),
+        }
+
+        thread::sleep(Duration::from_secs(GIF_REFRESH_SECONDS));
+    });
+}
+
+/// Escapes text and attribute values for Telegram's HTML parse mode.
+///
+/// Unlike Markdown, HTML allows escaped text inside of entities, so names
+/// containing underscores or asterisks don't break the formatting.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// A link to `url` for Telegram's HTML parse mode.
+fn html_link(text: &str, url: &str) -> String {
+    format!("<a href=\"{}\">{}</a>", escape_html(url), escape_html(text))
+}
+
+/// The HTML message sent when an inline query result is chosen.
+fn text_content(text: String) -> InputTextMessageContent {
+    InputTextMessageContent {
+        message_text: text,
+        parse_mode: Some(ParseMode::Html),
+        disable_web_page_preview: false,
+    }
+}
+
+/// The message announcing an event as HTML.
+fn event_text(event: &Event) -> String {
+    let details = event.repo.metadata
+        .as_ref()
+        .map(repository_details)
+        .unwrap_or_default();
+    let login = escape_html(&event.actor.display_login);
+    let repo_url = format!("https://github.com/{}", event.repo.name);
+    let text = match event.payload {
+        Payload::Fork { ref forkee } => format!(
+            "<b>{}</b> forked <i>{}</i> at {}!",
+            login,
+            escape_html(&event.repo.name),
+            html_link(&forkee.full_name, &forkee.html_url),
+        ),
+        Payload::Watch => format!(
+            "<b>{}</b> starred {}!",
+            login,
+            html_link(&event.repo.name, &repo_url),
+        ),
+        Payload::Create { .. } => format!(
+            "<b>{}</b> created the repository {}!",
+            login,
+            html_link(&event.repo.name, &repo_url),
+        ),
+        Payload::Release { ref release } => format!(
+            "<b>{}</b> released {} of <i>{}</i>!",
+            login,
+            html_link(&release.tag_name, &release.html_url),
+            escape_html(&event.repo.name),
+        ),
+        Payload::Public => format!(
+            "<b>{}</b> made {} public!",
+            login,
+            html_link(&event.repo.name, &repo_url),
+        ),
+    };
+
+    text + &details
+}
+
+/// Whether the text could be a GitHub login, which consists of up to 39
+/// alphanumeric characters or hyphens.
+fn is_github_login(text: &str) -> bool {
+    !text.is_empty()
+        && text.len() <= 39
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// A single line describing a fork event as HTML.
+fn fork_line(event: &Event) -> String {
+    let forkee = match event.payload {
+        Payload::Fork { ref forkee } => format!(" at {}", html_link(&forkee.full_name, &forkee.html_url)),
+        _ => String::new(),
+    };
+
+    format!(
+        "{} <b>{}</b> forked <i>{}</i>{}",
+        event.created_at.format("%Y-%m-%d"),
+        escape_html(&event.actor.display_login),
+        escape_html(&event.repo.name),
+        forkee,
+    )
+}
+
+/// Description, language, stars and license of a repository as HTML.
+fn repository_details(metadata: &RepositoryMetadata) -> String {
+    let mut details = String::new();
+
+    if let Some(ref description) = metadata.description {
+        details.push_str(&format!("\n\n{}", escape_html(description)));
+    }
+
+    let mut facts = Vec::new();
+    if let Some(ref language) = metadata.language {
+        facts.push(escape_html(language));
+    }
+    facts.push(format!("⭐ {}", metadata.stargazers_count));
+    if let Some(ref license) = metadata.license {
+        facts.push(escape_html(license));
+    }
+    details.push_str(&format!("\n{}", facts.join(" · ")));
+
+    details
+}
+
 impl InnerTelegramBot {
     fn check_admin(&self, message: &Message) -> bool {
         let authorized = match message.chat {
             Private(_) => true,
             _ => {
                 self.chats
-                    .get(&message.chat)
-                    .map(|admins| {
-                        admins
-                            .iter()
-                            .any(|item| *item == message.from)
-                    })
-                    .unwrap_or(false)
+                    .lock()
+                    .unwrap()
+                    .is_admin(message.chat.id(), message.from.id)
             },
         };
 
@@ -343,31 +1007,91 @@ impl InnerTelegramBot {
         authorized
     }
 
-    fn send_gif<'a>(&self, reply_chat: Option<MessageChat>) -> Result<(), GabelnError> {
+    /// Changing the tracked users affects every chat, so unlike the chat
+    /// settings it is reserved to configured admins and group admins, even
+    /// in private chats.
+    fn check_tracking_admin(&self, message: &Message) -> bool {
+        let configured = self.admins.contains(&i64::from(message.from.id));
+        let authorized = configured || match message.chat {
+            Group(_) | Supergroup(_) => self.chats
+                .lock()
+                .unwrap()
+                .is_admin(message.chat.id(), message.from.id),
+            _ => false,
+        };
+
+        if !authorized {
+            self.api.spawn(message.text_reply(
+                "You are not authorized to do this! Only administrators are allowed to track users!"
+            ));
+        }
+
+        authorized
+    }
+
+    /// Replies with the text if the changed chat was saved and with the error
+    /// otherwise, as a failed write must not stop the bot.
+    fn reply_saved<S: Into<String>>(&self, message: &Message, saved: Result<(), GabelnError>, text: S) {
+        match saved {
+            Ok(()) => self.api.spawn(message.text_reply(text)),
+            Err(e) => {
+                error!("Failed to save chat {}: {}", message.chat.id(), e);
+                self.api.spawn(message.text_reply(e.to_string()));
+            },
+        }
+    }
+
+    fn send_gif<'a>(&self, chats: &[ChatId]) -> Result<(), GabelnError> {
         let url = self.giphy.get_gif()?;
 
-        if let Some(chat) = reply_chat {
-            self.api.spawn(chat.document_url(url));
-        } else {
-            for chat in self.active_chats.iter() {
-                self.api.spawn(chat.document_url(url.clone()));
-            }
+        for chat in chats {
+            self.send_to(*chat, chat.document_url(url.clone()));
         }
 
         Ok(())
     }
 
-    fn command<'a>(&self, content: &'a str, chat: &MessageChat) -> Option<&'a str> {
+    /// Sends a request to a chat without being asked to and forgets the chat
+    /// if the bot was removed from it in the meantime.
+    fn send_to<Req: Request + 'static>(&self, chat: ChatId, request: Req) {
+        let chats = self.chats.clone();
+
+        current_thread::spawn(
+            self.api
+                .send(request)
+                .map(|_| ())
+                .or_else(move |e| {
+                    let reason = e.to_string();
+
+                    if reason.contains("bot was kicked") || reason.contains("bot was blocked") {
+                        info!("Removing chat {}: {}", chat, reason);
+                        if let Err(e) = chats.lock().unwrap().remove(chat) {
+                            error!("{:?}", e);
+                        }
+                    } else {
+                        warn!("Failed to send to chat {}: {}", chat, reason);
+                    }
+
+                    Ok(())
+                })
+        );
+    }
+
+    /// Parses a command addressed to this bot into its name and arguments.
+    fn command<'a>(&self, content: &'a str, chat: &MessageChat) -> Option<(&'a str, &'a str)> {
         debug!("Parsing command {}", content);
         if let Some(captures) = self.command_re.captures(content) {
+            let command = captures.get(1).map(|c| c.as_str());
+            let arguments = captures.get(3).map_or("", |c| c.as_str());
+
             if let Some(receiver) = captures.get(2) {
                 if let Some(ref username) = self.me.username {
                     if receiver.as_str() == username.as_str() {
-                        return captures.get(1).map(|c| c.as_str());
+                        return command.map(|command| (command, arguments));
                     }
                 }
             } else if let Private(_) = chat {
-                return captures.get(1).map(|c| c.as_str());
+                return command.map(|command| (command, arguments));
             }
         }
 
@@ -0,0 +1,98 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use serde_json;
+
+use error::GabelnError;
+use store::write_json_atomically;
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Changes {
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+/// Users tracked or untracked at runtime on top of the configured ones.
+///
+/// Only the changes are stored, so users added to the configuration later
+/// are still picked up.
+pub struct TrackedUsers {
+    path: PathBuf,
+    changes: Changes,
+}
+
+impl TrackedUsers {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, GabelnError> {
+        let path = path.as_ref().to_path_buf();
+        let mut changes = Changes::default();
+
+        if path.exists() {
+            debug!("Loading tracked users from {}", path.display());
+
+            let file = File::open(&path)
+                .map_err(|e| {
+                    error!("Failed to open tracked users: {}", e);
+                    GabelnError::FailedToReadTrackedUsers
+                })?;
+            changes = serde_json::from_reader(BufReader::new(file))
+                .map_err(|e| {
+                    error!("Failed to parse tracked users: {}", e);
+                    GabelnError::FailedToReadTrackedUsers
+                })?;
+
+            info!(
+                "Loaded {} tracked and {} untracked users",
+                changes.added.len(),
+                changes.removed.len(),
+            );
+        }
+
+        Ok(Self {
+            path: path,
+            changes: changes,
+        })
+    }
+
+    /// The configured users with all changes applied.
+    pub fn apply(&self, configured: &[String]) -> Vec<String> {
+        let mut users: Vec<String> = Vec::new();
+
+        for user in configured.iter().chain(self.changes.added.iter()) {
+            let removed = self.changes.removed.iter().any(|removed| removed.eq_ignore_ascii_case(user));
+            let known = users.iter().any(|known| known.eq_ignore_ascii_case(user));
+
+            if !removed && !known {
+                users.push(user.clone());
+            }
+        }
+
+        users
+    }
+
+    pub fn track(&mut self, login: &str) -> Result<(), GabelnError> {
+        self.changes.removed.retain(|removed| !removed.eq_ignore_ascii_case(login));
+        if !self.changes.added.iter().any(|added| added.eq_ignore_ascii_case(login)) {
+            self.changes.added.push(login.to_string());
+        }
+
+        self.save()
+    }
+
+    pub fn untrack(&mut self, login: &str) -> Result<(), GabelnError> {
+        self.changes.added.retain(|added| !added.eq_ignore_ascii_case(login));
+        if !self.changes.removed.iter().any(|removed| removed.eq_ignore_ascii_case(login)) {
+            self.changes.removed.push(login.to_string());
+        }
+
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), GabelnError> {
+        write_json_atomically(&self.path, &self.changes)
+            .map_err(|e| {
+                error!("Failed to write tracked users: {}", e);
+                GabelnError::FailedToWriteTrackedUsers
+            })
+    }
+}
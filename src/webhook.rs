@@ -0,0 +1,297 @@
+use rocket::{State, Request, Data, Outcome};
+use rocket::http::Status;
+use rocket::request::{self, FromRequest};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use hex;
+use serde_json;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use config::Config;
+use event_manager::EventManager;
+use events::{Actor, Event, EventType, Forkee, GitHubRepository, Payload, Repository, Source};
+
+/// Maximum size of a webhook payload GitHub sends.
+const MAX_PAYLOAD_SIZE: u64 = 25 * 1024 * 1024;
+
+pub struct WebhookHeaders {
+    event: Option<String>,
+    delivery: Option<String>,
+    signature: Option<String>,
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for WebhookHeaders {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, ()> {
+        let headers = request.headers();
+
+        Outcome::Success(WebhookHeaders {
+            event: headers.get_one("X-GitHub-Event").map(|value| value.to_string()),
+            delivery: headers.get_one("X-GitHub-Delivery").map(|value| value.to_string()),
+            signature: headers.get_one("X-Hub-Signature-256").map(|value| value.to_string()),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct WebhookUser {
+    login: String,
+    avatar_url: String,
+}
+
+#[derive(Deserialize)]
+struct ForkWebhook {
+    forkee: Forkee,
+    repository: GitHubRepository,
+    sender: WebhookUser,
+}
+
+impl ForkWebhook {
+    fn into_event(self, delivery: &str) -> Event {
+        Event {
+            id: format!("webhook-{}", delivery),
+            actor: Actor {
+                display_login: self.sender.login,
+                avatar_url: self.sender.avatar_url,
+            },
+            repo: Repository {
+                name: self.repository.full_name.clone(),
+                metadata: Some(self.repository.metadata()),
+            },
+            payload: Payload::Fork {
+                forkee: self.forkee,
+            },
+            created_at: Utc::now(),
+            source: Source::Repo(self.repository.full_name),
+        }
+    }
+}
+
+fn verify_signature(secret: &str, payload: &[u8], signature: &str) -> bool {
+    let signature = match hex::decode(signature.trim_start_matches("sha256=")) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    let mut mac = match Hmac::<Sha256>::new_varkey(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+
+    mac.input(payload);
+    mac.verify(&signature).is_ok()
+}
+
+#[post("/webhook/github", data = "<body>")]
+pub fn github(
+    headers: WebhookHeaders,
+    body: Data,
+    event_manager: State<Arc<Mutex<EventManager>>>,
+    config: State<Config>,
+) -> Status {
+    debug!("Handling /webhook/github request");
+
+    let secret = match config.webhook_secret {
+        Some(ref secret) => secret,
+        None => return Status::NotFound,
+    };
+
+    let mut payload = Vec::new();
+    if let Err(e) = body.open().take(MAX_PAYLOAD_SIZE).read_to_end(&mut payload) {
+        error!("Failed to read webhook payload: {}", e);
+        return Status::BadRequest;
+    }
+
+    let signature_valid = headers.signature
+        .as_ref()
+        .map_or(false, |signature| verify_signature(secret, &payload, signature));
+    if !signature_valid {
+        warn!("Rejecting webhook delivery with invalid signature");
+        return Status::Unauthorized;
+    }
+
+    match headers.event.as_ref().map(|event| event.as_str()) {
+        Some("ping") => {
+            info!("Received webhook ping");
+            Status::Ok
+        },
+        Some("fork") if config.event_types().contains(&EventType::Fork) => {
+            let webhook = match serde_json::from_slice::<ForkWebhook>(&payload) {
+                Ok(webhook) => webhook,
+                Err(e) => {
+                    error!("Failed to parse fork webhook: {}", e);
+                    return Status::BadRequest;
+                },
+            };
+            let delivery = match headers.delivery {
+                Some(ref delivery) => delivery,
+                None => {
+                    warn!("Rejecting fork webhook without delivery id");
+                    return Status::BadRequest;
+                },
+            };
+            let event = webhook.into_event(delivery);
+
+            info!("Received fork webhook: {}", event.summary());
+            match event_manager.inner().lock().unwrap().ingest(vec![event]) {
+                Ok(_) => Status::Ok,
+                Err(e) => {
+                    error!("{:?}", e);
+                    Status::InternalServerError
+                },
+            }
+        },
+        event => {
+            debug!("Ignoring webhook event {:?}", event);
+            Status::Accepted
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::{Client, LocalRequest};
+    use futures::sync::mpsc::UnboundedReceiver;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use hex;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::sync::{Arc, Mutex};
+
+    use config::Config;
+    use event_manager::EventManager;
+    use events::Event;
+
+    const SECRET: &str = "It's a Secret to Everybody";
+
+    const FORK: &str = r#"{
+        "forkee": {"id": 1, "full_name": "octocat/rust", "html_url": "https://github.com/octocat/rust"},
+        "repository": {
+            "full_name": "rust-lang/rust",
+            "description": "Empowering everyone to build reliable and efficient software.",
+            "language": "Rust",
+            "stargazers_count": 42,
+            "license": null,
+            "topics": []
+        },
+        "sender": {"login": "octocat", "avatar_url": "https://avatars.githubusercontent.com/u/583231"}
+    }"#;
+
+    /// A client for the webhook route with an empty history. The receiver
+    /// has to be kept, as publishing fails once it is dropped.
+    fn client(name: &str) -> (Client, UnboundedReceiver<Event>) {
+        let dir = env::temp_dir().join(format!("gabeln-webhook-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut config = Config::default();
+        config.webhook_secret = Some(SECRET.to_string());
+        config.events_file = dir.join("events.json").to_string_lossy().into_owned();
+        config.seen_events_file = dir.join("seen-events.jsonl").to_string_lossy().into_owned();
+        config.tracked_users_file = dir.join("tracked-users.json").to_string_lossy().into_owned();
+        let (event_manager, recv) = EventManager::new(config.clone()).unwrap();
+
+        let rocket = rocket::ignite()
+            .mount("/", routes![super::github])
+            .manage(Arc::new(Mutex::new(event_manager)))
+            .manage(config);
+
+        (Client::new(rocket).unwrap(), recv)
+    }
+
+    fn sign(payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_varkey(SECRET.as_bytes()).unwrap();
+        mac.input(payload.as_bytes());
+
+        format!("sha256={}", hex::encode(mac.result().code()))
+    }
+
+    fn delivery<'c>(client: &'c Client, event: &str, payload: &str) -> LocalRequest<'c> {
+        client.post("/webhook/github")
+            .header(ContentType::JSON)
+            .header(Header::new("X-GitHub-Event", event.to_string()))
+            .body(payload)
+    }
+
+    fn stored_events(client: &Client) -> usize {
+        client.rocket()
+            .state::<Arc<Mutex<EventManager>>>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .events
+            .len()
+    }
+
+    #[test]
+    fn accepts_signed_forks() {
+        let (client, _recv) = client("signed");
+
+        let response = delivery(&client, "fork", FORK)
+            .header(Header::new("X-GitHub-Delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958"))
+            .header(Header::new("X-Hub-Signature-256", sign(FORK)))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(stored_events(&client), 1);
+    }
+
+    #[test]
+    fn rejects_wrong_signatures() {
+        let (client, _recv) = client("wrong-signature");
+
+        let response = delivery(&client, "fork", FORK)
+            .header(Header::new("X-GitHub-Delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958"))
+            .header(Header::new("X-Hub-Signature-256", sign("{}")))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = delivery(&client, "fork", FORK)
+            .header(Header::new("X-GitHub-Delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958"))
+            .header(Header::new("X-Hub-Signature-256", "sha256=not-hex"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(stored_events(&client), 0);
+    }
+
+    #[test]
+    fn rejects_missing_signatures() {
+        let (client, _recv) = client("missing-signature");
+
+        let response = delivery(&client, "fork", FORK)
+            .header(Header::new("X-GitHub-Delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(stored_events(&client), 0);
+    }
+
+    #[test]
+    fn rejects_forks_without_delivery_id() {
+        let (client, _recv) = client("missing-delivery");
+
+        let response = delivery(&client, "fork", FORK)
+            .header(Header::new("X-Hub-Signature-256", sign(FORK)))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(stored_events(&client), 0);
+    }
+
+    #[test]
+    fn answers_pings() {
+        let (client, _recv) = client("ping");
+        let payload = r#"{"zen": "Keep it logically awesome."}"#;
+
+        let response = delivery(&client, "ping", payload)
+            .header(Header::new("X-Hub-Signature-256", sign(payload)))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = delivery(&client, "ping", payload).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+}